@@ -0,0 +1,346 @@
+/// Event director, fires scheduled and adaptive world events configured per scenario
+use bevy::prelude::*;
+use float_ord::FloatOrd;
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+
+use crate::{
+    draw, game,
+    hivemind::{Base, Drone, Forage, Ledger},
+    world::{self, DeRon, Flag, WorldMap},
+    AppState,
+};
+
+/// World events the director can trigger
+#[derive(Debug, Clone, Copy)]
+pub enum DirectorEvent {
+    SpawnMultivac(IVec2),
+    Eruption(IVec2),
+    Bloom {
+        center: IVec2,
+        radius: i32,
+        count: u32,
+    },
+}
+
+/// Scenario file form of a director event
+///
+/// `kind` is one of "multivac", "eruption" or "bloom". `radius` and `count` only matter for
+/// blooms
+#[derive(Debug, Clone, DeRon)]
+pub struct Action {
+    pub kind: String,
+    pub location: (i32, i32),
+    pub radius: i32,
+    pub count: u32,
+}
+
+impl Action {
+    pub fn event(&self) -> Result<DirectorEvent, String> {
+        let location = IVec2::new(self.location.0, self.location.1);
+        match self.kind.as_str() {
+            "multivac" => Ok(DirectorEvent::SpawnMultivac(location)),
+            "eruption" => Ok(DirectorEvent::Eruption(location)),
+            "bloom" if self.radius < 0 => Err(format!("negative bloom radius {}", self.radius)),
+            "bloom" => Ok(DirectorEvent::Bloom {
+                center: location,
+                radius: self.radius,
+                count: self.count,
+            }),
+            kind => Err(format!("unknown director event kind '{}'", kind)),
+        }
+    }
+}
+
+/// Fires once the game clock reaches `at` seconds
+#[derive(Debug, Clone, DeRon)]
+pub struct Scheduled {
+    pub at: f32,
+    pub action: Action,
+}
+
+/// Fires whenever its condition holds, at most once every `cooldown` seconds
+///
+/// `when` is one of "food_above", "food_below" or "drones_above", compared against `threshold`
+#[derive(Debug, Clone, DeRon)]
+pub struct Rule {
+    pub when: String,
+    pub threshold: f32,
+    pub cooldown: f32,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Condition {
+    /// total food stored by all colonies
+    FoodAbove(f32),
+    FoodBelow(f32),
+    /// total drones alive across all colonies
+    DronesAbove(f32),
+}
+
+impl Rule {
+    pub fn condition(&self) -> Result<Condition, String> {
+        match self.when.as_str() {
+            "food_above" => Ok(Condition::FoodAbove(self.threshold)),
+            "food_below" => Ok(Condition::FoodBelow(self.threshold)),
+            "drones_above" => Ok(Condition::DronesAbove(self.threshold)),
+            when => Err(format!("unknown director rule condition '{}'", when)),
+        }
+    }
+}
+
+/// Scenario file contents
+#[derive(DeRon)]
+pub struct Scenario {
+    pub schedule: Vec<Scheduled>,
+    pub rules: Vec<Rule>,
+}
+
+pub struct Director {
+    /// sorted by game time
+    schedule: Vec<(f32, DirectorEvent)>,
+    next: usize,
+    rules: Vec<(Condition, f32, DirectorEvent)>,
+    last_fired: Vec<Option<f32>>,
+}
+
+impl Director {
+    /// Load and validate the scenario. Malformed events and rules are reported and left out, so
+    /// they can't fail mid-game
+    // TODO: remove assets hardcoded path
+    pub fn load() -> Self {
+        let bytes = include_str!("../assets/director.ron");
+        let scenario: Scenario =
+            DeRon::deserialize_ron(bytes).expect("ERROR: failed to load director scenario");
+        Self::new(&scenario)
+    }
+
+    fn new(scenario: &Scenario) -> Self {
+        let mut schedule: Vec<(f32, DirectorEvent)> = scenario
+            .schedule
+            .iter()
+            .filter_map(|s| match s.action.event() {
+                Ok(event) => Some((s.at, event)),
+                Err(e) => {
+                    error!("skipping director event scheduled at {}: {}", s.at, e);
+                    None
+                }
+            })
+            .collect();
+        schedule.sort_by_key(|(at, _)| FloatOrd(*at));
+
+        let rules: Vec<(Condition, f32, DirectorEvent)> = scenario
+            .rules
+            .iter()
+            .filter_map(|r| match (r.condition(), r.action.event()) {
+                (Ok(condition), Ok(event)) => Some((condition, r.cooldown, event)),
+                (Err(e), _) | (_, Err(e)) => {
+                    error!("skipping director rule on '{}': {}", r.when, e);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            schedule,
+            next: 0,
+            last_fired: vec![None; rules.len()],
+            rules,
+        }
+    }
+}
+
+/// Fire every scheduled event that is due and every rule whose condition holds
+pub fn direct(
+    director: ResMut<Director>,
+    time: Res<game::Time>,
+    bases: Query<&Base>,
+    drones: Query<(), With<Drone>>,
+    mut events: EventWriter<DirectorEvent>,
+) {
+    let director = director.into_inner();
+    while let Some((at, event)) = director.schedule.get(director.next) {
+        if *at > time.0 {
+            break;
+        }
+        debug!("director firing scheduled {:?}", event);
+        events.send(*event);
+        director.next += 1;
+    }
+
+    let food: u32 = bases
+        .iter()
+        .map(|base| base.food.total())
+        .sum();
+    let drone_count = drones.iter().count();
+    for (i, (condition, cooldown, event)) in director.rules.iter().enumerate() {
+        let ready = match director.last_fired[i] {
+            Some(fired) => time.0 - fired >= *cooldown,
+            None => true,
+        };
+        let holds = match *condition {
+            Condition::FoodAbove(threshold) => food as f32 > threshold,
+            Condition::FoodBelow(threshold) => (food as f32) < threshold,
+            Condition::DronesAbove(threshold) => drone_count as f32 > threshold,
+        };
+        if ready && holds {
+            debug!("director firing {:?} on {:?}", event, condition);
+            events.send(*event);
+            director.last_fired[i] = Some(time.0);
+        }
+    }
+}
+
+/// Apply director events to the world
+pub fn handle_events(
+    mut commands: Commands,
+    mut map: ResMut<WorldMap>,
+    forage: Res<Forage>,
+    mut ledger: ResMut<Ledger>,
+    mut events: EventReader<DirectorEvent>,
+) {
+    let mut rng = SmallRng::from_entropy();
+    for event in events.iter() {
+        match *event {
+            DirectorEvent::SpawnMultivac(location) => {
+                // only on open ground, so repeated firings don't stack multivacs on one tile
+                match map.get_ivec2(location) {
+                    Some(flags) if flags.is_empty() => {}
+                    _ => continue,
+                }
+                map[location] |= Flag::MULTIVAC;
+                commands
+                    .spawn()
+                    .insert(world::Multivac)
+                    .insert(world::Position(location.as_vec2()));
+                draw::ping_long(&mut commands, location);
+            }
+            DirectorEvent::Eruption(location) => {
+                // raise a volcano, unless something that can't be buried is in the way
+                match map.get_ivec2(location) {
+                    Some(flags) if flags.intersects(Flag::VOLCANO) => {}
+                    Some(flags)
+                        if flags.intersects(Flag::COLONY_ALL | Flag::MULTIVAC | Flag::OUTPOST) =>
+                    {
+                        continue
+                    }
+                    Some(flags) => {
+                        ledger.retile(forage.on_tile(flags), 0);
+                        map[location] = Flag::VOLCANO;
+                        commands
+                            .spawn()
+                            .insert(world::Volcano)
+                            .insert(world::Position(location.as_vec2()));
+                    }
+                    None => continue,
+                }
+                draw::ping_long(&mut commands, location);
+            }
+            DirectorEvent::Bloom {
+                center,
+                radius,
+                count,
+            } => {
+                for _ in 0..count {
+                    let pos = center
+                        + IVec2::new(
+                            rng.gen_range(-radius..=radius),
+                            rng.gen_range(-radius..=radius),
+                        );
+                    match map.get_ivec2(pos) {
+                        Some(flags) if flags.is_empty() => {
+                            map[pos] = Flag::FLOWER;
+                            map[pos].set_resource_quantity(world::Flower::MAX);
+                            ledger.retile(0, forage.on_tile(map[pos]));
+                            commands
+                                .spawn()
+                                .insert(world::Flower)
+                                .insert(world::Position(pos.as_vec2()));
+                        }
+                        _ => continue,
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct Plugin;
+
+/// The director's events are applied to the world right after they fire, and anything reacting
+/// to what changed runs after that
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum DirectorOrder {
+    Direct,
+    Apply,
+}
+
+impl bevy::prelude::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Director::load())
+            .add_event::<DirectorEvent>()
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .before(world::Order::WorldUpdate)
+                    .with_system(direct.label(DirectorOrder::Direct))
+                    .with_system(
+                        handle_events
+                            .label(DirectorOrder::Apply)
+                            .after(DirectorOrder::Direct),
+                    ),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(kind: &str, radius: i32) -> Action {
+        Action {
+            kind: kind.into(),
+            location: (10, 10),
+            radius,
+            count: 4,
+        }
+    }
+
+    #[test]
+    fn malformed_scenario_entries_are_left_out() {
+        let scenario = Scenario {
+            schedule: vec![
+                Scheduled {
+                    at: 10.0,
+                    action: action("bloom", -3),
+                },
+                Scheduled {
+                    at: 20.0,
+                    action: action("meteor", 0),
+                },
+                Scheduled {
+                    at: 5.0,
+                    action: action("bloom", 3),
+                },
+            ],
+            rules: vec![
+                Rule {
+                    when: "food_sideways".into(),
+                    threshold: 0.0,
+                    cooldown: 0.0,
+                    action: action("eruption", 0),
+                },
+                Rule {
+                    when: "drones_above".into(),
+                    threshold: 100.0,
+                    cooldown: 60.0,
+                    action: action("multivac", 0),
+                },
+            ],
+        };
+        let director = Director::new(&scenario);
+        assert_eq!(director.schedule.len(), 1);
+        assert_eq!(director.rules.len(), 1);
+        assert_eq!(director.last_fired.len(), 1);
+    }
+}
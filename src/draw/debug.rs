@@ -44,15 +44,25 @@ pub fn setup_fields(
     repellent_field: Query<(Entity, &hivemind::VectorField), With<field_systems::Repellent>>,
     attractor_field: Query<(Entity, &hivemind::VectorField), With<field_systems::Attractor>>,
     wall_field: Query<(Entity, &hivemind::ScalarField), With<field_systems::Wall>>,
+    discovery_field: Query<(Entity, &hivemind::ScalarField), With<field_systems::Discovery>>,
+    alarm_field: Query<(Entity, &hivemind::ScalarField), With<field_systems::Alarm>>,
 ) {
     let food_field = food_field.single().0;
     let density_field = density_field.single().0;
     let repellent_field = repellent_field.single().0;
     let attractor_field = attractor_field.single().0;
     let wall_field = wall_field.single().0;
+    let discovery_field = discovery_field.single().0;
+    let alarm_field = alarm_field.single().0;
 
     commands.entity(food_field).insert(DebugColor(Color::RED));
     commands.entity(wall_field).insert(DebugColor(Color::BLACK));
+    commands
+        .entity(discovery_field)
+        .insert(DebugColor(Color::CYAN));
+    commands
+        .entity(alarm_field)
+        .insert(DebugColor(Color::PURPLE));
     /*
     commands
         .entity(density_field)
@@ -1,7 +1,12 @@
 mod debug;
 mod setup;
 
-use crate::{grid, world, AppState};
+use crate::{
+    grid,
+    hivemind::{colony::Hibernating, Base, Drone},
+    texture::{TextureAtlases, TextureHandles},
+    world, AppState,
+};
 use bevy::prelude::*;
 use bevy_prototype_debug_lines::DebugLinesPlugin;
 
@@ -55,9 +60,49 @@ pub fn world_sprites_no_offset(mut query: Query<(&world::Position, &mut Transfor
 }
 
 /// Updates all health bars based on resource value drawn to the world with offset (things centered at tile)
-pub fn healthbars(map: Res<world::WorldMap>, mut query: Query<(&world::Position, &mut Transform), With<HealthBarSprite>>) {
-    for (pos, mut transform) in query.iter_mut() {
-            transform.scale = Vec3::new((map[pos.0].get_resource_quantity() * 100 / world::Colony::MAX) as f32 + 2.0, 8.0, 0.0);
+pub fn healthbars(bases: Query<&Base>, mut query: Query<(&Parent, &mut Transform), With<HealthBarSprite>>) {
+    for (parent, mut transform) in query.iter_mut() {
+        if let Ok(base) = bases.get(parent.0) {
+            transform.scale = Vec3::new((base.food.total() * 100 / world::Colony::MAX) as f32 + 2.0, 8.0, 0.0);
+        }
+    }
+}
+
+/// Grey out hibernating colonies
+pub fn hibernation(mut query: Query<(&mut Sprite, Option<&Hibernating>), With<world::Colony>>) {
+    for (mut sprite, hibernating) in query.iter_mut() {
+        sprite.color = match hibernating {
+            Some(_) => Color::GRAY,
+            None => Color::WHITE,
+        };
+    }
+}
+
+/// Swap the sprites of drones and colonies whose colony changed, such as after a merge
+#[allow(clippy::type_complexity)]
+pub fn recolor(
+    sprite_sheets: Res<TextureAtlases>,
+    textures: Res<TextureHandles>,
+    mut drones: Query<
+        (&world::Flag, &mut Handle<TextureAtlas>),
+        (With<Drone>, Changed<world::Flag>),
+    >,
+    mut colonies: Query<
+        (&world::Flag, &mut Handle<Image>, &Children),
+        (With<world::Colony>, Changed<world::Flag>),
+    >,
+    mut bars: Query<&mut Sprite, With<HealthBarSprite>>,
+) {
+    for (colony, mut sheet) in drones.iter_mut() {
+        *sheet = sprite_sheets[setup::bee_sheet(*colony)].clone();
+    }
+    for (colony, mut texture, children) in colonies.iter_mut() {
+        *texture = textures[setup::colony_texture(*colony)].clone();
+        for child in children.iter() {
+            if let Ok(mut bar) = bars.get_mut(*child) {
+                bar.color = setup::colony_color(*colony);
+            }
+        }
     }
 }
 
@@ -117,7 +162,11 @@ impl bevy::prelude::Plugin for Plugin {
                 .with_system(setup::colony)
                 .with_system(setup::flower)
                 .with_system(setup::tree)
+                .with_system(setup::stump)
                 .with_system(setup::volcano)
+                .with_system(setup::predator)
+                .with_system(setup::lava)
+                .with_system(setup::rock)
                 .with_system(setup::multivac)
                 .with_system(setup::wire)
                 .with_system(setup::outpost),
@@ -130,7 +179,11 @@ impl bevy::prelude::Plugin for Plugin {
                 .with_system(setup::colony)
                 .with_system(setup::flower)
                 .with_system(setup::tree)
+                .with_system(setup::stump)
                 .with_system(setup::volcano)
+                .with_system(setup::predator)
+                .with_system(setup::lava)
+                .with_system(setup::rock)
                 .with_system(setup::multivac)
                 .with_system(setup::wire)
                 .with_system(setup::outpost)
@@ -138,6 +191,8 @@ impl bevy::prelude::Plugin for Plugin {
                 .with_system(world_sprites_offset)
                 .with_system(world_sprites_no_offset)
                 .with_system(healthbars)
+                .with_system(hibernation)
+                .with_system(recolor)
                 .with_system(despawn_temp),
         );
         app.add_plugin(DebugLinesPlugin::default())
@@ -4,21 +4,56 @@ use bevy::prelude::*;
 
 use crate::{
     grid,
-    hivemind::colony,
+    hivemind::{caste, colony},
     multivac::{Dir, WireKind},
+    predator,
     texture::{TextureAtlases, TextureHandles},
     world::{self, Flag},
 };
 
 use super::{Delay, Ping, WorldSpriteNoOffset, WorldSpriteOffset, WORLD_DRAW_SCALE};
 
+/// Sprite sheet for drones of a colony, merged colonies share the bee_all sheet
+pub fn bee_sheet(colony: Flag) -> &'static str {
+    match colony & Flag::COLONY_ALL {
+        Flag::COLONY_C => "bee_c",
+        Flag::COLONY_M => "bee_m",
+        Flag::COLONY_Y => "bee_y",
+        flag if !flag.is_empty() => "bee_all",
+        _ => panic!("A drone was instantiated with invalid colony bit flags"),
+    }
+}
+
+/// Texture for a colony tile, merged colonies share the plain colony texture
+pub fn colony_texture(colony: Flag) -> &'static str {
+    match colony & Flag::COLONY_ALL {
+        Flag::COLONY_C => "colony_c",
+        Flag::COLONY_M => "colony_m",
+        Flag::COLONY_Y => "colony_y",
+        flag if !flag.is_empty() => "colony",
+        _ => panic!("invalid flags {:?} for drawing colony", colony),
+    }
+}
+
+/// Health bar color of a colony
+pub fn colony_color(colony: Flag) -> Color {
+    match colony & Flag::COLONY_ALL {
+        Flag::COLONY_Y => Color::rgba(1.0, 0.74, 0.0, 0.8),
+        Flag::COLONY_C => Color::rgba(0.12, 0.86, 0.63, 0.8),
+        Flag::COLONY_M => Color::rgba(0.89, 0.24, 0.75, 0.8),
+        flag if !flag.is_empty() => Color::rgba(0.9, 0.9, 0.9, 0.8),
+        _ => panic!("invalid flags {:?} for drawing colony", colony),
+    }
+}
+
 /// add data for any undrawn hivemind sprites
 pub fn hivemind(
     mut commands: Commands,
     sprite_sheets: Res<TextureAtlases>,
-    drones: Query<(Entity, &colony::Drone, &world::Position, &world::Flag), Without<Transform>>,
+    castes: Res<caste::Config>,
+    drones: Query<(Entity, &colony::Drone, &world::Position, &world::Flag, &caste::Caste), Without<Transform>>,
 ) {
-    for (entity, _, pos, colony) in drones.iter() {
+    for (entity, _, pos, colony, caste) in drones.iter() {
         commands
             .entity(entity)
             .insert_bundle(SpriteSheetBundle {
@@ -27,16 +62,10 @@ pub fn hivemind(
                     scale: Vec3::new(WORLD_DRAW_SCALE, WORLD_DRAW_SCALE, 0.0),
                     ..Default::default()
                 },
-                texture_atlas: match *colony & (Flag::COLONY_C | Flag::COLONY_M | Flag::COLONY_Y) {
-                    //@TODO do bee_all sheet :)
-                    Flag::COLONY_ALL => sprite_sheets["bee_all"].clone(),
-                    Flag::COLONY_C => sprite_sheets["bee_c"].clone(),
-                    Flag::COLONY_M => sprite_sheets["bee_m"].clone(),
-                    Flag::COLONY_Y => sprite_sheets["bee_y"].clone(),
-                    _ => panic!("A drone was instantiated with invalid colony bit flags"),
-                },
+                texture_atlas: sprite_sheets[bee_sheet(*colony)].clone(),
                 sprite: TextureAtlasSprite {
                     index: 0,
+                    color: castes.traits(*caste).tint,
                     ..Default::default()
                 },
 
@@ -46,6 +75,32 @@ pub fn hivemind(
     }
 }
 
+/// Add data for any undrawn predator sprites
+pub fn predator(
+    mut commands: Commands,
+    sprite_sheets: Res<TextureAtlases>,
+    query: Query<(Entity, &predator::Predator, &world::Position), Without<Transform>>,
+) {
+    for (entity, _, pos) in query.iter() {
+        commands
+            .entity(entity)
+            .insert_bundle(SpriteSheetBundle {
+                transform: Transform {
+                    translation: grid::world_to_iso(pos.0),
+                    scale: Vec3::new(WORLD_DRAW_SCALE * 1.4, WORLD_DRAW_SCALE * 1.4, 0.0),
+                    ..Default::default()
+                },
+                texture_atlas: sprite_sheets["hornet"].clone(),
+                sprite: TextureAtlasSprite {
+                    index: 0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(WorldSpriteNoOffset);
+    }
+}
+
 /// Add data for any undrawn flower sprites
 pub fn flower(
     mut commands: Commands,
@@ -94,6 +149,32 @@ pub fn tree(
     }
 }
 
+/// Add data for any undrawn stump sprites
+pub fn stump(
+    mut commands: Commands,
+    textures: Res<TextureHandles>,
+    query: Query<(Entity, &world::Stump, &world::Position), Without<Transform>>,
+) {
+    for (entity, _, pos) in query.iter() {
+        commands
+            .entity(entity)
+            .insert_bundle(SpriteBundle {
+                transform: Transform {
+                    translation: grid::world_to_iso(pos.0),
+                    scale: Vec3::new(WORLD_DRAW_SCALE, WORLD_DRAW_SCALE, 0.0),
+                    ..Default::default()
+                },
+                sprite: Sprite {
+                    color: Color::rgb(0.4, 0.26, 0.13),
+                    ..Default::default()
+                },
+                texture: textures["ping"].clone(),
+                ..Default::default()
+            })
+            .insert(WorldSpriteOffset);
+    }
+}
+
 /// Add data for any undrawn volcano sprites
 pub fn volcano(
     mut commands: Commands,
@@ -116,14 +197,68 @@ pub fn volcano(
     }
 }
 
+/// Add data for any undrawn lava sprites
+pub fn lava(
+    mut commands: Commands,
+    textures: Res<TextureHandles>,
+    query: Query<(Entity, &world::Lava, &world::Position), Without<Transform>>,
+) {
+    for (entity, _, pos) in query.iter() {
+        commands
+            .entity(entity)
+            .insert_bundle(SpriteBundle {
+                transform: Transform {
+                    translation: grid::world_to_iso(pos.0),
+                    scale: Vec3::new(WORLD_DRAW_SCALE, WORLD_DRAW_SCALE, 0.0),
+                    ..Default::default()
+                },
+                sprite: Sprite {
+                    color: Color::ORANGE_RED,
+                    ..Default::default()
+                },
+                texture: textures["ping"].clone(),
+                ..Default::default()
+            })
+            .insert(WorldSpriteOffset);
+    }
+}
+
+/// Add data for any undrawn rock sprites
+pub fn rock(
+    mut commands: Commands,
+    textures: Res<TextureHandles>,
+    query: Query<(Entity, &world::Rock, &world::Position), Without<Transform>>,
+) {
+    for (entity, _, pos) in query.iter() {
+        commands
+            .entity(entity)
+            .insert_bundle(SpriteBundle {
+                transform: Transform {
+                    translation: grid::world_to_iso(pos.0),
+                    scale: Vec3::new(WORLD_DRAW_SCALE, WORLD_DRAW_SCALE, 0.0),
+                    ..Default::default()
+                },
+                sprite: Sprite {
+                    color: Color::DARK_GRAY,
+                    ..Default::default()
+                },
+                texture: textures["ping"].clone(),
+                ..Default::default()
+            })
+            .insert(WorldSpriteOffset);
+    }
+}
+
 /// Add data for any undrawn colony sprites
 pub fn colony(
     mut commands: Commands,
     textures: Res<TextureHandles>,
-    query: Query<(Entity, &world::Colony, &world::Flag, &world::Position), Without<Transform>>,
-    map: Res<world::WorldMap>,
+    query: Query<
+        (Entity, &world::Colony, &world::Flag, &world::Position, &colony::Base),
+        Without<Transform>,
+    >,
 ) {
-    for (entity, _, flag, pos) in query.iter() {
+    for (entity, _, flag, pos, base) in query.iter() {
         commands
             .entity(entity)
             .insert_bundle(SpriteBundle {
@@ -132,16 +267,7 @@ pub fn colony(
                     scale: Vec3::new(WORLD_DRAW_SCALE, WORLD_DRAW_SCALE, 0.0),
                     ..Default::default()
                 },
-                texture: match *flag {
-                    world::Flag::COLONY_C => textures["colony_c"].clone(),
-                    world::Flag::COLONY_M => textures["colony_m"].clone(),
-                    world::Flag::COLONY_Y => textures["colony_y"].clone(),
-                    world::Flag::COLONY_ALL => textures["colony"].clone(),
-                    _ => panic!(
-                        "invalid flags {:?} for drawing colony at {}, {}",
-                        flag, pos.0.x, pos.0.y
-                    ),
-                },
+                texture: textures[colony_texture(*flag)].clone(),
                 ..Default::default()
             })
             .insert(WorldSpriteOffset)
@@ -149,22 +275,13 @@ pub fn colony(
                 parent
                     .spawn_bundle(SpriteBundle {
                         sprite: Sprite {
-                            color: match *flag {
-                                world::Flag::COLONY_Y => Color::rgba(1.0, 0.74, 0.0, 0.8),
-                                world::Flag::COLONY_C => Color::rgba(0.12, 0.86, 0.63, 0.8),
-                                world::Flag::COLONY_M => Color::rgba(0.89, 0.24, 0.75, 0.8),
-                                world::Flag::COLONY_ALL => Color::rgba(0.9, 0.9, 0.9, 0.8),
-                                _ => panic!(
-                                    "invalid flags {:?} for drawing colony at {}, {}",
-                                    flag, pos.0.x, pos.0.y
-                                ),
-                            },
+                            color: colony_color(*flag),
                             ..Default::default()
                         },
                         transform: Transform {
                             translation: super::HealthBarSprite::OFFSET,
                             scale: Vec3::new(
-                                0.5 * map[pos.0].get_resource_quantity() as f32,
+                                0.5 * base.food.total() as f32,
                                 10.0,
                                 0.0,
                             ),
@@ -0,0 +1,372 @@
+/// Flower and forest ecology. Visiting drones pollinate flowers into new ones nearby, depleted
+/// flowers grow back over time, and forests slowly spread, age, and regrow from the stumps fallen
+/// and harvested trees leave
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+use std::collections::HashMap;
+
+use crate::{
+    hivemind::{Forage, GatherEvent, Ledger},
+    multivac,
+    world::{self, Flag, WorldMap, DIRS},
+    AppState,
+};
+
+#[derive(Debug)]
+pub struct Config {
+    /// chance that a single drone visit seeds a new flower
+    pub seed_chance: f64,
+    /// how many tiles away from the visited flower a seed can land
+    pub seed_radius: i32,
+    /// food in a freshly seeded flower
+    pub seed_quantity: u32,
+    /// seconds before a depleted flower grows back
+    pub regrow_time: f32,
+    /// the real-time period between forest updates
+    pub forest_clock: f32,
+    /// chance each forest update that a tree seeds one of its empty neighbors
+    pub spread_chance: f64,
+    /// seconds a tree stands before it falls
+    pub tree_lifespan: f32,
+    /// forest updates before a stump grows back into a tree
+    pub stump_regrow: u32,
+    /// sap in a fresh stump
+    pub stump_sap: u32,
+}
+
+impl Config {
+    pub fn default() -> Self {
+        Self {
+            seed_chance: 0.05,
+            seed_radius: 2,
+            seed_quantity: world::Flower::MAX / 4,
+            regrow_time: 60.0,
+            forest_clock: 5.0,
+            spread_chance: 0.01,
+            tree_lifespan: 900.0,
+            stump_regrow: 60,
+            stump_sap: 200,
+        }
+    }
+}
+
+/// State of the meadow across the whole world
+#[derive(Default)]
+pub struct Meadow {
+    /// depleted flowers waiting to grow back
+    sprouts: HashMap<IVec2, Timer>,
+    /// fraction of the world covered in flowers, as of the last forest tick
+    pub density: f32,
+    /// flowers seeded by drone visits
+    pub seeded: u32,
+    pub regrown: u32,
+}
+
+/// Time until the forest next grows
+pub struct ForestClock(pub Timer);
+
+/// How long a tree has been standing, in seconds
+#[derive(Component)]
+pub struct Age(pub f32);
+
+/// Marks a tree that fell of old age, so it isn't mistaken for a harvested one before it despawns
+#[derive(Component)]
+pub struct Fallen;
+
+/// trees along the border wall in the world, they never grow, age or fall
+fn on_border(map: &WorldMap, pos: IVec2) -> bool {
+    pos.x <= 0 || pos.y <= 0 || pos.x >= map.w() as i32 - 1 || pos.y >= map.h() as i32 - 1
+}
+
+/// Leave a stump where a tree fell, or under the outpost that took the last of it until it moves on
+fn stump(cell: &mut Flag, sap: u32) {
+    *cell |= Flag::STUMP;
+    cell.set_resource_quantity(sap);
+}
+
+fn plant_tree(
+    commands: &mut Commands,
+    map: &mut WorldMap,
+    ledger: &mut Ledger,
+    forage: &Forage,
+    pos: IVec2,
+) {
+    let before = forage.on_tile(map[pos]);
+    map[pos] = Flag::TREE;
+    map[pos].set_resource_quantity(world::Tree::MAX);
+    ledger.retile(before, forage.on_tile(map[pos]));
+    commands
+        .spawn()
+        .insert(world::Tree)
+        .insert(world::Position(pos.as_vec2()));
+}
+
+fn plant(
+    commands: &mut Commands,
+    map: &mut WorldMap,
+    ledger: &mut Ledger,
+    forage: &Forage,
+    pos: IVec2,
+    quantity: u32,
+) {
+    let before = forage.on_tile(map[pos]);
+    map[pos] = Flag::FLOWER;
+    map[pos].set_resource_quantity(quantity);
+    ledger.retile(before, forage.on_tile(map[pos]));
+    commands
+        .spawn()
+        .insert(world::Flower)
+        .insert(world::Position(pos.as_vec2()));
+}
+
+/// Drone visits to a flower may seed a new one on a nearby empty tile. Visits to a depleted flower
+/// leave a sprout behind to regrow
+pub fn pollinate(
+    mut commands: Commands,
+    mut map: ResMut<WorldMap>,
+    config: Res<Config>,
+    forage: Res<Forage>,
+    mut ledger: ResMut<Ledger>,
+    mut meadow: ResMut<Meadow>,
+    mut events: EventReader<GatherEvent>,
+) {
+    let mut rng = SmallRng::from_entropy();
+    for event in events.iter() {
+        let visited = event.pos.as_ivec2();
+        match map.get_ivec2(visited) {
+            Some(flags) if flags.intersects(Flag::FLOWER) => {
+                if !rng.gen_bool(config.seed_chance) {
+                    continue;
+                }
+                let r = config.seed_radius;
+                let pos = visited + IVec2::new(rng.gen_range(-r..=r), rng.gen_range(-r..=r));
+                match map.get_ivec2(pos) {
+                    Some(flags) if flags.is_empty() => {
+                        plant(
+                            &mut commands,
+                            &mut map,
+                            &mut ledger,
+                            &forage,
+                            pos,
+                            config.seed_quantity,
+                        );
+                        meadow.seeded += 1;
+                    }
+                    _ => continue,
+                }
+            }
+            Some(flags) if flags.is_empty() => {
+                meadow
+                    .sprouts
+                    .entry(visited)
+                    .or_insert_with(|| Timer::from_seconds(config.regrow_time, false));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Grow sprouts back into flowers, and keep track of how dense the meadow is
+pub fn regrow(
+    mut commands: Commands,
+    mut map: ResMut<WorldMap>,
+    time_step: Res<world::TimeStep>,
+    forage: Res<Forage>,
+    mut ledger: ResMut<Ledger>,
+    clock: Res<ForestClock>,
+    meadow: ResMut<Meadow>,
+) {
+    let meadow = meadow.into_inner();
+    let time_step = time_step.into_inner();
+
+    let mut grown = Vec::new();
+    meadow.sprouts.retain(|pos, timer| {
+        // something else took the spot
+        if !map[*pos].is_empty() {
+            return false;
+        }
+        if timer.tick(time_step.into()).finished() {
+            grown.push(*pos);
+            return false;
+        }
+        true
+    });
+    for pos in grown {
+        plant(
+            &mut commands,
+            &mut map,
+            &mut ledger,
+            &forage,
+            pos,
+            world::Flower::MAX,
+        );
+        meadow.regrown += 1;
+    }
+
+    // counting the whole map is too slow to do every frame
+    if !clock.0.just_finished() {
+        return;
+    }
+    let mut flowers = 0;
+    for y in 0..map.h() {
+        for x in 0..map.w() {
+            if map[y][x].intersects(Flag::FLOWER) {
+                flowers += 1;
+            }
+        }
+    }
+    meadow.density = flowers as f32 / (map.w() * map.h()) as f32;
+}
+
+/// Age, spread and fell trees. Fallen and harvested trees leave a stump behind, the colonies' only
+/// source of sap, that eventually regrows
+#[allow(clippy::too_many_arguments)]
+pub fn forest(
+    mut commands: Commands,
+    mut map: ResMut<WorldMap>,
+    time_step: Res<world::TimeStep>,
+    config: Res<Config>,
+    forage: Res<Forage>,
+    mut ledger: ResMut<Ledger>,
+    mut clock: ResMut<ForestClock>,
+    mut harvests: EventReader<multivac::HarvestEvent>,
+    mut trees: Query<
+        (Entity, &world::Position, Option<&mut Age>),
+        (With<world::Tree>, Without<Fallen>),
+    >,
+    mut stumps: Query<(&mut world::Stump, &world::Position)>,
+) {
+    let tick = clock.0.tick(time_step.into_inner().into()).just_finished();
+    let mut rng = SmallRng::from_entropy();
+
+    let mut saplings = Vec::new();
+    for (entity, pos, age) in trees.iter_mut() {
+        let pos = pos.0.as_ivec2();
+        let mut age = match age {
+            Some(age) => age,
+            None => {
+                // stagger ages so the forest doesn't fall all at once
+                if !on_border(&map, pos) {
+                    commands
+                        .entity(entity)
+                        .insert(Age(rng.gen_range(0.0..config.tree_lifespan)));
+                }
+                continue;
+            }
+        };
+
+        let flags = map[pos];
+        if !flags.intersects(Flag::TREE) {
+            continue;
+        }
+
+        if !tick {
+            continue;
+        }
+        age.0 += config.forest_clock;
+        if age.0 > config.tree_lifespan {
+            map[pos] = Flag::EMPTY;
+            stump(&mut map[pos], config.stump_sap);
+            ledger.retile(forage.on_tile(flags), forage.on_tile(map[pos]));
+            commands.entity(entity).insert(Fallen);
+            commands
+                .spawn()
+                .insert(world::Stump {
+                    regrow: config.stump_regrow,
+                })
+                .insert(world::Position(pos.as_vec2()));
+            continue;
+        }
+        if rng.gen_bool(config.spread_chance) {
+            saplings.push(pos + DIRS[rng.gen_range(0..DIRS.len())]);
+        }
+    }
+
+    for harvest in harvests.iter() {
+        if !harvest.kind.intersects(Flag::TREE) || map[harvest.pos].intersects(Flag::STUMP) {
+            continue;
+        }
+        let before = forage.on_tile(map[harvest.pos]);
+        stump(&mut map[harvest.pos], config.stump_sap);
+        ledger.retile(before, forage.on_tile(map[harvest.pos]));
+        commands
+            .spawn()
+            .insert(world::Stump {
+                regrow: config.stump_regrow,
+            })
+            .insert(world::Position(harvest.pos.as_vec2()));
+    }
+
+    if !tick {
+        return;
+    }
+
+    for pos in saplings {
+        match map.get_ivec2(pos) {
+            Some(flags) if flags.is_empty() && !on_border(&map, pos) => {
+                plant_tree(&mut commands, &mut map, &mut ledger, &forage, pos);
+            }
+            _ => continue,
+        }
+    }
+
+    for (mut stump, pos) in stumps.iter_mut() {
+        if !map[pos.0].intersects(Flag::STUMP) {
+            continue;
+        }
+        if stump.grow() {
+            plant_tree(&mut commands, &mut map, &mut ledger, &forage, pos.0.as_ivec2());
+        }
+    }
+}
+
+pub struct Plugin;
+
+impl bevy::prelude::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        let config = Config::default();
+        app.insert_resource(ForestClock(Timer::from_seconds(config.forest_clock, true)))
+            .insert_resource(config)
+            .init_resource::<Meadow>()
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .after(world::Order::WorldUpdate)
+                    .with_system(pollinate)
+                    .with_system(regrow)
+                    .with_system(forest),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn harvested_tree_leaves_a_stump_that_regrows() {
+        let config = Config::default();
+        let mut cell = Flag::TREE | Flag::OUTPOST | Flag::CONNECTED;
+        cell.set_resource_quantity(world::Tree::MAX);
+
+        // the outpost eats the tree down, and a stump takes its place under the outpost
+        let kind = loop {
+            if let Some(kind) = multivac::graze(&mut cell, 50) {
+                break kind;
+            }
+        };
+        assert_eq!(kind, Flag::TREE);
+        stump(&mut cell, config.stump_sap);
+        assert!(cell.contains(Flag::STUMP | Flag::OUTPOST));
+
+        // the stump outlasts the outpost, then grows back
+        multivac::vacate(&mut cell);
+        assert_eq!(cell & Flag::KIND_MASK, Flag::STUMP);
+        assert_eq!(cell.get_resource_quantity(), config.stump_sap);
+        let mut remains = world::Stump {
+            regrow: config.stump_regrow,
+        };
+        let updates = (0..).find(|_| remains.grow()).unwrap();
+        assert_eq!(updates, config.stump_regrow);
+    }
+}
@@ -0,0 +1,56 @@
+/// Alarm pheromone. Drones raise it where one of them died, touched lava or multivac wires, or ran
+/// into a rival colony, and other drones steer clear of it or flee
+use bevy::prelude::*;
+
+use super::{field_systems, Drone, ScalarField};
+use crate::world::{self, Flag, WorldMap};
+
+#[derive(Debug)]
+pub struct Config {
+    /// alarm raised where a drone dies
+    pub death: f32,
+    /// alarm raised every update a drone spends on lava or a wire
+    pub touch: f32,
+    /// alarm raised where drones of rival colonies meet
+    pub encounter: f32,
+    /// alarm gradient steep enough to make a drone flee
+    pub danger: f32,
+}
+
+impl Config {
+    pub fn default() -> Self {
+        Self {
+            death: 20.0,
+            touch: 5.0,
+            encounter: 5.0,
+            danger: 2.0,
+        }
+    }
+}
+
+/// something alarming happened at `pos`
+pub struct AlarmEvent {
+    pub pos: Vec2,
+    pub strength: f32,
+}
+
+/// Raise the alarm wherever something alarming happened, and under every drone touching danger
+pub fn raise(
+    config: Res<Config>,
+    map: Res<WorldMap>,
+    mut field: Query<&mut ScalarField, With<field_systems::Alarm>>,
+    drones: Query<&world::Position, With<Drone>>,
+    mut events: EventReader<AlarmEvent>,
+) {
+    let mut field = field.single_mut();
+    for event in events.iter() {
+        field[event.pos] += event.strength;
+    }
+    for pos in drones.iter() {
+        if let Some(flags) = map.get_vec2(pos.0) {
+            if flags.intersects(Flag::LAVA | Flag::WIRE) {
+                field[pos.0] += config.touch;
+            }
+        }
+    }
+}
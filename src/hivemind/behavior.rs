@@ -0,0 +1,347 @@
+/// Drone state machine loaded from a behavior file: which signals drones mix in each state, when
+/// they turn autonomous, and the transitions between states. The file is watched and reloaded
+/// while the game runs
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::time::SystemTime;
+
+use super::colony::DroneState;
+use crate::world::DeRon;
+
+// TODO: remove assets hardcoded path
+const PATH: &str = "assets/behavior.ron";
+
+/// Something a drone senses, weighted into where it heads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Term {
+    /// straight towards home, the way the drone integrated from its path
+    Home,
+    /// towards the patch the drone remembers gathering from
+    Memory,
+    Food,
+    Attractor,
+    Repellent,
+    Density,
+    Discovery,
+    /// away from drones right next to this one
+    Separation,
+    /// the heading of nestmates right next to this one
+    Alignment,
+    /// away from the crowd at a congested nest entrance
+    Congestion,
+    /// up the alarm pheromone, towards danger
+    Alarm,
+    /// the summed signal, only meaningful for autonomy
+    Signal,
+}
+
+impl Term {
+    fn parse(name: &str) -> Result<Self, String> {
+        Ok(match name {
+            "home" => Term::Home,
+            "memory" => Term::Memory,
+            "food" => Term::Food,
+            "attractor" => Term::Attractor,
+            "repellent" => Term::Repellent,
+            "density" => Term::Density,
+            "discovery" => Term::Discovery,
+            "separation" => Term::Separation,
+            "alignment" => Term::Alignment,
+            "congestion" => Term::Congestion,
+            "alarm" => Term::Alarm,
+            "signal" => Term::Signal,
+            name => return Err(format!("unknown signal term '{}'", name)),
+        })
+    }
+}
+
+/// What a drone knows about itself and its tile when deciding on its next state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    /// on a tile of its own colony
+    AtHome,
+    /// on a tile of any colony
+    AtNest,
+    /// on a tile with food it can gather
+    Food,
+    /// can't carry any more
+    Full,
+    /// carrying anything at all
+    Carrying,
+    Autonomous,
+    /// standing on a steep alarm gradient
+    Danger,
+}
+
+impl Condition {
+    fn parse(name: &str) -> Result<Self, String> {
+        Ok(match name {
+            "at_home" => Condition::AtHome,
+            "at_nest" => Condition::AtNest,
+            "food" => Condition::Food,
+            "full" => Condition::Full,
+            "carrying" => Condition::Carrying,
+            "autonomous" => Condition::Autonomous,
+            "danger" => Condition::Danger,
+            name => return Err(format!("unknown condition '{}'", name)),
+        })
+    }
+}
+
+/// The conditions that hold for a single drone this update
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Facts {
+    pub at_home: bool,
+    pub at_nest: bool,
+    pub food: bool,
+    pub full: bool,
+    pub carrying: bool,
+    pub autonomous: bool,
+    pub danger: bool,
+}
+
+impl Facts {
+    fn holds(&self, condition: Condition) -> bool {
+        match condition {
+            Condition::AtHome => self.at_home,
+            Condition::AtNest => self.at_nest,
+            Condition::Food => self.food,
+            Condition::Full => self.full,
+            Condition::Carrying => self.carrying,
+            Condition::Autonomous => self.autonomous,
+            Condition::Danger => self.danger,
+        }
+    }
+}
+
+fn parse_state(name: &str) -> Result<DroneState, String> {
+    DroneState::ALL
+        .iter()
+        .copied()
+        .find(|state| format!("{:?}", state) == name)
+        .ok_or_else(|| format!("unknown drone state '{}'", name))
+}
+
+/// Behavior file contents
+#[derive(DeRon)]
+pub struct BehaviorFile {
+    pub states: Vec<StateFile>,
+    pub transitions: Vec<TransitionFile>,
+}
+
+#[derive(DeRon)]
+pub struct StateFile {
+    pub name: String,
+    pub signal: Vec<(String, f32)>,
+    pub autonomy: Vec<String>,
+}
+
+#[derive(DeRon)]
+pub struct TransitionFile {
+    pub from: String,
+    pub when: Vec<String>,
+    pub to: String,
+}
+
+#[derive(Debug, Default)]
+struct StateBehavior {
+    signal: Vec<(Term, f32)>,
+    autonomy: Vec<Term>,
+}
+
+#[derive(Debug)]
+struct Transition {
+    from: DroneState,
+    /// conditions with whether they have to hold or not
+    when: Vec<(Condition, bool)>,
+    to: DroneState,
+}
+
+#[derive(Debug)]
+pub struct Behavior {
+    states: HashMap<DroneState, StateBehavior>,
+    transitions: Vec<Transition>,
+}
+
+impl Behavior {
+    /// Load and validate the behavior file shipped with the game, a malformed one fails loudly at
+    /// startup
+    pub fn load() -> Self {
+        let bytes = include_str!("../../assets/behavior.ron");
+        Self::parse(bytes).expect("ERROR: failed to load drone behavior")
+    }
+
+    /// Parse a behavior file, checking every state is defined exactly once and every name in it
+    /// is known
+    pub fn parse(bytes: &str) -> Result<Self, String> {
+        let file: BehaviorFile = DeRon::deserialize_ron(bytes).map_err(|e| format!("{:?}", e))?;
+
+        let mut states = HashMap::new();
+        for s in file.states.iter() {
+            let state = parse_state(&s.name)?;
+            let mut signal = Vec::new();
+            for (name, weight) in s.signal.iter() {
+                match Term::parse(name)? {
+                    Term::Signal => {
+                        return Err(format!("state {} mixes the signal into itself", s.name))
+                    }
+                    term => signal.push((term, *weight)),
+                }
+            }
+            let autonomy = s
+                .autonomy
+                .iter()
+                .map(|name| Term::parse(name))
+                .collect::<Result<_, _>>()?;
+            if states
+                .insert(state, StateBehavior { signal, autonomy })
+                .is_some()
+            {
+                return Err(format!("state {} is defined more than once", s.name));
+            }
+        }
+        if let Some(state) = DroneState::ALL.iter().find(|s| !states.contains_key(s)) {
+            return Err(format!("state {:?} is not defined", state));
+        }
+
+        let mut transitions = Vec::new();
+        for t in file.transitions.iter() {
+            let when = t
+                .when
+                .iter()
+                .map(|name| match name.strip_prefix('!') {
+                    Some(name) => Condition::parse(name).map(|c| (c, false)),
+                    None => Condition::parse(name).map(|c| (c, true)),
+                })
+                .collect::<Result<_, _>>()?;
+            transitions.push(Transition {
+                from: parse_state(&t.from)?,
+                when,
+                to: parse_state(&t.to)?,
+            });
+        }
+
+        Ok(Self {
+            states,
+            transitions,
+        })
+    }
+
+    /// The state a drone moves to, the first transition out of its state whose conditions hold
+    pub fn next(&self, state: DroneState, facts: &Facts) -> DroneState {
+        self.transitions
+            .iter()
+            .filter(|t| t.from == state)
+            .find(|t| t.when.iter().all(|(c, holds)| facts.holds(*c) == *holds))
+            .map(|t| t.to)
+            .unwrap_or(state)
+    }
+
+    /// Where a drone in a state heads, weighing what it senses. None keeps it in place
+    pub fn signal(&self, state: DroneState, sense: impl Fn(Term) -> Vec2) -> Option<Vec2> {
+        let behavior = &self.states[&state];
+        if behavior.signal.is_empty() {
+            return None;
+        }
+        Some(
+            behavior
+                .signal
+                .iter()
+                .fold(Vec2::ZERO, |signal, (term, weight)| signal + sense(*term) * *weight),
+        )
+    }
+
+    /// Whether a drone in a state is left to wander, with nothing it senses stronger than
+    /// `threshold`
+    pub fn autonomous(
+        &self,
+        state: DroneState,
+        signal: Vec2,
+        threshold: f32,
+        sense: impl Fn(Term) -> Vec2,
+    ) -> bool {
+        let autonomy = &self.states[&state].autonomy;
+        !autonomy.is_empty()
+            && autonomy.iter().all(|term| {
+                let strength = match term {
+                    Term::Signal => signal,
+                    term => sense(*term),
+                };
+                strength.length() < threshold
+            })
+    }
+}
+
+/// Checks the behavior file for changes now and then
+pub struct Watch {
+    clock: Timer,
+    modified: Option<SystemTime>,
+}
+
+fn modified() -> Option<SystemTime> {
+    fs::metadata(PATH).and_then(|m| m.modified()).ok()
+}
+
+impl Default for Watch {
+    fn default() -> Self {
+        Self {
+            clock: Timer::from_seconds(1.0, true),
+            modified: modified(),
+        }
+    }
+}
+
+/// Swap in the behavior file whenever it changes on disk, keeping the old behavior if the new one
+/// doesn't validate
+pub fn reload(time: Res<Time>, mut watch: ResMut<Watch>, mut behavior: ResMut<Behavior>) {
+    if !watch.clock.tick(time.delta()).just_finished() {
+        return;
+    }
+    let modified = modified();
+    if modified.is_none() || modified == watch.modified {
+        return;
+    }
+    watch.modified = modified;
+
+    match fs::read_to_string(PATH)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| Behavior::parse(&bytes))
+    {
+        Ok(reloaded) => {
+            *behavior = reloaded;
+            info!("reloaded drone behavior");
+        }
+        Err(e) => error!("failed to reload drone behavior, keeping the old one: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_behavior_validates() {
+        let behavior = Behavior::load();
+        let facts = Facts {
+            food: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            behavior.next(DroneState::ToFood, &facts),
+            DroneState::Gathering
+        );
+        assert_eq!(
+            behavior.next(DroneState::Gathering, &Facts::default()),
+            DroneState::ToHomeNoFood
+        );
+        assert!(behavior.signal(DroneState::Resting, |_| Vec2::ONE).is_none());
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        let bytes = include_str!("../../assets/behavior.ron");
+        assert!(Behavior::parse(&bytes.replace("\"at_home\"", "\"at_hom\"")).is_err());
+        assert!(Behavior::parse(&bytes.replace("\"Dead\"", "\"Undead\"")).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+/// Drone castes. Scouts roam far and mark where they find food, foragers follow trails to bring it
+/// home, and guards patrol around their nest and go after rivals troubling it
+use bevy::prelude::*;
+use float_ord::FloatOrd;
+
+use crate::world::Flag;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Caste {
+    Scout,
+    Forager,
+    Guard,
+}
+
+impl Caste {
+    pub const ALL: [Caste; 3] = [Caste::Scout, Caste::Forager, Caste::Guard];
+}
+
+/// How drones of a single caste behave
+#[derive(Debug)]
+pub struct Traits {
+    /// share of a colony's drones raised into the caste
+    pub share: f32,
+    pub move_speed: f32,
+    pub turn_speed: f32,
+    pub chaos: f32,
+    pub explore_threshold: f32,
+    /// how strongly pheromone trails pull the caste towards food
+    pub trail: f32,
+    /// whether the caste gathers food at all
+    pub forages: bool,
+    pub tint: Color,
+}
+
+#[derive(Debug)]
+pub struct Config {
+    pub scout: Traits,
+    pub forager: Traits,
+    pub guard: Traits,
+    /// discovery pheromone a scout leaves on food it finds
+    pub discovery: f32,
+    /// how far from home guards patrol, in tiles
+    pub patrol_radius: f32,
+    /// how much more likely a guard is to win an encounter
+    pub guard_advantage: f32,
+    /// how much a starving colony favours scouts, and a colony under attack favours guards
+    pub boost: f32,
+    /// seconds an encounter keeps drawing guards to it
+    pub alarm: f32,
+}
+
+impl Config {
+    pub fn default() -> Self {
+        Self {
+            scout: Traits {
+                share: 0.15,
+                move_speed: 1.2,
+                turn_speed: 0.2,
+                chaos: 2.0,
+                explore_threshold: 0.4,
+                trail: 0.2,
+                forages: true,
+                tint: Color::rgb(0.7, 0.85, 1.0),
+            },
+            forager: Traits {
+                share: 0.75,
+                move_speed: 1.0,
+                turn_speed: 0.15,
+                chaos: 1.0,
+                explore_threshold: 0.1,
+                trail: 1.5,
+                forages: true,
+                tint: Color::WHITE,
+            },
+            guard: Traits {
+                share: 0.1,
+                move_speed: 0.8,
+                turn_speed: 0.15,
+                chaos: 1.5,
+                explore_threshold: 0.1,
+                trail: 0.0,
+                forages: false,
+                tint: Color::rgb(1.0, 0.6, 0.6),
+            },
+            discovery: 5.0,
+            patrol_radius: 6.0,
+            guard_advantage: 0.2,
+            boost: 2.0,
+            alarm: 10.0,
+        }
+    }
+
+    pub fn traits(&self, caste: Caste) -> &Traits {
+        match caste {
+            Caste::Scout => &self.scout,
+            Caste::Forager => &self.forager,
+            Caste::Guard => &self.guard,
+        }
+    }
+
+    /// The caste a newly raised drone joins, whichever is furthest short of the share its colony
+    /// wants. `counts` are the colony's drones indexed by caste
+    pub fn assign(&self, counts: [usize; 3], hungry: bool, threatened: bool) -> Caste {
+        let mut shares = Caste::ALL.map(|caste| self.traits(caste).share);
+        if hungry {
+            shares[Caste::Scout as usize] *= self.boost;
+        }
+        if threatened {
+            shares[Caste::Guard as usize] *= self.boost;
+        }
+        let total_share: f32 = shares.iter().sum();
+        let total = (counts.iter().sum::<usize>() + 1) as f32;
+        let shortfall =
+            |caste: Caste| shares[caste as usize] / total_share * total - counts[caste as usize] as f32;
+        Caste::ALL
+            .iter()
+            .copied()
+            .max_by_key(|caste| FloatOrd(shortfall(*caste)))
+            .unwrap_or(Caste::Forager)
+    }
+}
+
+/// Somewhere a colony's drone was recently attacked or robbed
+#[derive(Debug)]
+pub struct Threat {
+    pub pos: Vec2,
+    pub colony: Flag,
+    /// seconds left until guards stop answering it
+    pub left: f32,
+}
+
+/// Recent attacks guards respond to
+#[derive(Default)]
+pub struct Threats(pub Vec<Threat>);
+
+impl Threats {
+    pub fn against(&self, colony: Flag) -> bool {
+        self.0.iter().any(|threat| threat.colony.intersects(colony))
+    }
+
+    /// The closest threat to a colony within `radius` of its nest
+    pub fn near(&self, colony: Flag, home: Vec2, radius: f32) -> Option<Vec2> {
+        self.0
+            .iter()
+            .filter(|threat| threat.colony.intersects(colony) && threat.pos.distance(home) <= radius)
+            .map(|threat| threat.pos)
+            .min_by_key(|pos| FloatOrd(pos.distance(home)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assign_fills_shortest_caste() {
+        let config = Config::default();
+        assert_eq!(config.assign([0, 0, 0], false, false), Caste::Forager);
+        assert_eq!(config.assign([0, 9, 1], false, false), Caste::Scout);
+        assert_eq!(config.assign([2, 9, 0], false, false), Caste::Guard);
+        assert_eq!(config.assign([2, 9, 1], false, true), Caste::Guard);
+    }
+}
@@ -1,10 +1,22 @@
 use std::f32::consts::FRAC_PI_4;
+use std::collections::HashMap;
+use std::time::Duration;
 
 use bevy::prelude::*;
 use bevy::tasks::prelude::*;
 use rand::prelude::*;
 
-use super::{field_systems, DepositEvent, GatherEvent, ScalarField, VectorField};
+use super::{
+    alarm,
+    behavior::{Behavior, Facts, Term},
+    caste::{self, Caste, Threats},
+    disease::{self, Infection},
+    explore::{self, Walk},
+    neighbors::{self, Sensed, SpatialHash},
+    queen::Queen,
+    field_systems, Deaths, DepositEvent, Forage, GatherEvent, Ledger, Resource, ScalarField,
+    Stores, VectorField,
+};
 use crate::{
     world::{self, Flag, WorldMap},
     game,
@@ -29,17 +41,22 @@ const PROD_HIVE_LOCATION: (f32, f32) = (
 #[derive(Debug)]
 pub struct Config {
     pub starting: usize,
+    /// most drones a single colony can raise
     pub max: usize,
+    /// seconds between drones for a colony with `world::Colony::MAX` food in store
     pub spawn_rate: f32,
-    pub drone_cost: u32,
+    /// bounds on how much stored food speeds up or slows down spawning
+    pub min_growth: f32,
+    pub max_growth: f32,
+    /// drones' worth of every needed resource a hibernating colony has to store before waking
+    pub wake_reserve: u32,
+    /// share of its drones a hibernating colony loses each spawn tick with its stores empty
+    pub starvation: f32,
+    /// food a drone can carry home in one trip
+    pub capacity: u32,
     pub location_y: Vec2,
     pub location_m: Vec2,
     pub location_c: Vec2,
-    pub color: Color,
-    pub move_speed: f32,
-    pub turn_speed: f32,
-    pub chaos: f32,
-    pub explore_threshold: f32,
 }
 
 impl Config {
@@ -48,24 +65,44 @@ impl Config {
             starting: 1,
             max: 10000,
             spawn_rate: 1.0,
-            drone_cost: 9,
+            min_growth: 0.25,
+            max_growth: 4.0,
+            wake_reserve: 5,
+            starvation: 0.05,
+            capacity: 3,
             location_y: Vec2::new(TEST_HIVE_LOCATION.0, TEST_HIVE_LOCATION.1),
             location_c: Vec2::new(STAGING_HIVE_LOCATION.0, STAGING_HIVE_LOCATION.1),
             location_m: Vec2::new(PROD_HIVE_LOCATION.0, PROD_HIVE_LOCATION.1),
-            color: Color::rgb(1.0, 1.0, 1.0),
-            move_speed: 1.0,
-            turn_speed: 0.15,
-            chaos: 1.0,
-            explore_threshold: 0.1,
         }
     }
+
+    /// Drones a hibernating colony of `population` loses to starvation each spawn tick, more the
+    /// bigger it is and the further its stores are from waking it. `shortfall` runs from 0, all
+    /// but ready to wake, to 1, nothing in store
+    pub fn starved(&self, population: usize, shortfall: f32) -> usize {
+        let starved = (population as f32 * self.starvation * shortfall.clamp(0.0, 1.0)).ceil();
+        (starved as usize).min(population)
+    }
 }
 
+/// A colony's nest and everything it has in store, keyed by the colony it belongs to
 #[derive(Component)]
 pub struct Base {
+    pub colony: Flag,
     pub location: Vec2,
     pub size: f32,
-    pub food: f32,
+    pub food: Stores,
+}
+
+impl Base {
+    pub fn new(colony: Flag, location: Vec2, food: Stores) -> Self {
+        Self {
+            colony,
+            location,
+            size: 1.0,
+            food,
+        }
+    }
 }
 
 #[derive(Component)]
@@ -77,6 +114,43 @@ pub struct Drone {
 #[derive(Component)]
 pub struct Colonist {
     pub home: Vec2,
+    /// the colony entity whose base this drone feeds
+    pub nest: Entity,
+}
+
+/// What a drone is carrying home, a single resource kind at a time
+#[derive(Component, Default)]
+pub struct Payload {
+    pub resource: Option<Resource>,
+    pub amount: u32,
+}
+
+/// What a drone remembers of where it has been
+#[derive(Component, Default)]
+pub struct Memory {
+    /// center of the last patch the drone gathered from, until it finds the patch depleted
+    pub patch: Option<Vec2>,
+    /// how much of a full patch was left there when the drone last gathered
+    pub richness: f32,
+    /// way back home, integrated from every step the drone takes
+    pub home: Vec2,
+    /// the home `home` leads to, a drone moved to another nest learns the way anew
+    nest: Vec2,
+    /// where the drone was when `home` was last integrated
+    last: Vec2,
+}
+
+impl Memory {
+    /// Add up the drone's steps since the last update into its way home
+    fn integrate(&mut self, pos: Vec2, nest: Vec2) {
+        if self.nest != nest {
+            self.nest = nest;
+            self.home = nest - pos;
+        } else {
+            self.home -= pos - self.last;
+        }
+        self.last = pos;
+    }
 }
 
 // colony colors, used to easily find and despawn the drones when game over occurs
@@ -96,16 +170,21 @@ impl Drone {
     }
 
     pub fn should_face_left(&self) -> bool {
-        // https://stackoverflow.com/questions/6247153/angle-from-2d-unit-vector/6247163#6247163
-        // :prayge:
-        let radians = (-self.direction.y).atan2(self.direction.x);
-
-        radians >= (3. * FRAC_PI_4) || radians <= -FRAC_PI_4
+        faces_left(self.direction)
     }
 }
 
+/// Whether a sprite heading in `direction` should be drawn facing left
+pub fn faces_left(direction: Vec2) -> bool {
+    // https://stackoverflow.com/questions/6247153/angle-from-2d-unit-vector/6247163#6247163
+    // :prayge:
+    let radians = (-direction.y).atan2(direction.x);
+
+    radians >= (3. * FRAC_PI_4) || radians <= -FRAC_PI_4
+}
+
 #[allow(dead_code)]
-#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DroneState {
     ToHome,
     ToHomeNoFood,
@@ -114,14 +193,39 @@ pub enum DroneState {
     Gathering,
     Depositing,
     Resting,
+    Fleeing,
     Dead,
 }
 
+impl DroneState {
+    pub const ALL: [DroneState; 9] = [
+        DroneState::ToHome,
+        DroneState::ToHomeNoFood,
+        DroneState::ToFood,
+        DroneState::Exploring,
+        DroneState::Gathering,
+        DroneState::Depositing,
+        DroneState::Resting,
+        DroneState::Fleeing,
+        DroneState::Dead,
+    ];
+}
+
 #[derive(Component)]
 pub struct ColonyClock(pub Timer);
 
+/// A starving colony stops raising drones and loses them one by one until it's fed again
+#[derive(Component)]
+pub struct Hibernating;
+
 /// Top level colony setup fn
-pub fn setup(mut commands: Commands, mut map: ResMut<world::WorldMap>, config: Res<Config>) {
+pub fn setup(
+    mut commands: Commands,
+    mut map: ResMut<world::WorldMap>,
+    config: Res<Config>,
+    forage: Res<Forage>,
+    mut ledger: ResMut<Ledger>,
+) {
     debug!("setting up colonies with config: {:?}", config);
     // NOTE: unrolled loop to be able to put specific marker structs into colony and colonist
     // componenets
@@ -133,23 +237,31 @@ pub fn setup(mut commands: Commands, mut map: ResMut<world::WorldMap>, config: R
         _ => unreachable!(),
     };
 
-    commands
+    let nest = commands
         .spawn()
         .insert(world::Colony)
         .insert(ColonyClock(Timer::from_seconds(config.spawn_rate, true)))
+        .insert(Base::new(colony_flag, pos, forage.starting(colony_flag)))
         .insert(world::Position(pos))
         .insert(colony_flag.clone())
-        .insert(M);
+        .insert(M)
+        .id();
     map[pos] |= colony_flag;
-    map[pos].set_resource_quantity(1000);
+    ledger.founded += forage.starting(colony_flag).total();
     for _ in 0..config.starting {
         commands
             .spawn()
             .insert(Drone::new())
             .insert(DroneState::Exploring)
+            .insert(Caste::Forager)
+            .insert(Payload::default())
+            .insert(Memory::default())
+            .insert(Walk::default())
+            .insert(Infection::default())
             .insert(world::Position(pos))
             .insert(Colonist {
                 home: pos + Vec2::new(0.5, 0.5),
+                nest,
             })
             .insert(colony_flag.clone())
             .insert(Y);
@@ -163,23 +275,31 @@ pub fn setup(mut commands: Commands, mut map: ResMut<world::WorldMap>, config: R
         _ => unreachable!(),
     };
 
-    commands
+    let nest = commands
         .spawn()
         .insert(world::Colony)
         .insert(ColonyClock(Timer::from_seconds(config.spawn_rate, true)))
+        .insert(Base::new(colony_flag, pos, forage.starting(colony_flag)))
         .insert(world::Position(pos))
         .insert(colony_flag.clone())
-        .insert(M);
+        .insert(M)
+        .id();
     map[pos] |= colony_flag;
-    map[pos].set_resource_quantity(1000);
+    ledger.founded += forage.starting(colony_flag).total();
     for _ in 0..config.starting {
         commands
             .spawn()
             .insert(Drone::new())
             .insert(DroneState::Exploring)
+            .insert(Caste::Forager)
+            .insert(Payload::default())
+            .insert(Memory::default())
+            .insert(Walk::default())
+            .insert(Infection::default())
             .insert(world::Position(pos))
             .insert(Colonist {
                 home: pos + Vec2::new(0.5, 0.5),
+                nest,
             })
             .insert(colony_flag.clone())
             .insert(M);
@@ -193,23 +313,31 @@ pub fn setup(mut commands: Commands, mut map: ResMut<world::WorldMap>, config: R
         _ => unreachable!(),
     };
 
-    commands
+    let nest = commands
         .spawn()
         .insert(world::Colony)
         .insert(ColonyClock(Timer::from_seconds(config.spawn_rate, true)))
+        .insert(Base::new(colony_flag, pos, forage.starting(colony_flag)))
         .insert(world::Position(pos))
         .insert(colony_flag.clone())
-        .insert(C);
+        .insert(C)
+        .id();
     map[pos] |= colony_flag;
-    map[pos].set_resource_quantity(1000);
+    ledger.founded += forage.starting(colony_flag).total();
     for _ in 0..config.starting {
         commands
             .spawn()
             .insert(Drone::new())
             .insert(DroneState::Exploring)
+            .insert(Caste::Forager)
+            .insert(Payload::default())
+            .insert(Memory::default())
+            .insert(Walk::default())
+            .insert(Infection::default())
             .insert(world::Position(pos))
             .insert(Colonist {
                 home: pos + Vec2::new(0.5, 0.5),
+                nest,
             })
             .insert(colony_flag.clone()).insert(C);
         }
@@ -217,89 +345,190 @@ pub fn setup(mut commands: Commands, mut map: ResMut<world::WorldMap>, config: R
     info!("setup colonies");
 }
 
-/// colony tries to spawn a new drone, if it runs out of resources it dies
+fn game_over_text(commands: &mut Commands, asset_server: &AssetServer) {
+    commands.spawn_bundle(TextBundle {
+        style: Style {
+            align_self: AlignSelf::FlexEnd,
+            position_type: PositionType::Absolute,
+            position: Rect {
+                bottom: Val::Px(110.0),
+                right: Val::Px(10.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        // Use the `Text::with_section` constructor
+        text: Text::with_section(
+            // Accepts a `String` or any type that converts into a `String`, such as `&str`
+            "GAME OVER!",
+            TextStyle {
+                font: asset_server.load("fonts/monogram.ttf"),
+                font_size: 100.0,
+                color: Color::WHITE,
+            },
+            // Note: You can use `Default::default()` in place of the `TextAlignment`
+            TextAlignment {
+                horizontal: HorizontalAlign::Right,
+                ..Default::default()
+            },
+        ),
+        ..Default::default()
+    });
+}
+
+/// colony raises drones while it can afford them, faster the more food it has in store. A starving
+/// colony hibernates and slowly loses drones until it's fed back up, or dies out
+#[allow(clippy::too_many_arguments)]
 pub fn update_colony(
     mut commands: Commands,
     mut map: ResMut<world::WorldMap>,
     time_step: Res<world::TimeStep>,
     config: Res<Config>,
+    castes: Res<caste::Config>,
+    threats: Res<Threats>,
+    forage: Res<Forage>,
+    mut ledger: ResMut<Ledger>,
+    mut deaths: ResMut<Deaths>,
     cheat: Res<world::Cheat>,
     asset_server: Res<AssetServer>,
     mut game_timer: ResMut<game::GameTimer>,
-    mut query: Query<(Entity, &mut ColonyClock, &world::Position, &world::Flag)>,
+    drones: Query<(Entity, &Colonist, &Caste), With<Drone>>,
+    mut query: Query<(
+        Entity,
+        &mut ColonyClock,
+        &mut Base,
+        &world::Position,
+        &world::Flag,
+        Option<&Hibernating>,
+    )>,
 ) {
     let time_step = time_step.into_inner();
-    for (entity, mut clock, pos, flag) in query.iter_mut() {
-        if clock.0.tick(time_step.into()).just_finished() {
-            let resource = map[pos.0].get_resource_quantity();
-            if resource > 0 {
-                map[pos.0]
-                    .set_resource_quantity(resource - std::cmp::min(resource, config.drone_cost));
+    let mut nests: HashMap<Flag, usize> = HashMap::new();
+    for (_, _, base, _, _, _) in query.iter() {
+        *nests.entry(base.colony).or_default() += 1;
+    }
+
+    for (entity, mut clock, mut base, pos, flag, hibernating) in query.iter_mut() {
+        let growth = (base.food.total() as f32 / world::Colony::MAX as f32)
+            .clamp(config.min_growth, config.max_growth);
+        clock
+            .0
+            .set_duration(Duration::from_secs_f32(config.spawn_rate / growth));
+        if !clock.0.tick(time_step.into()).just_finished() {
+            continue;
+        }
+
+        let needs = forage.needs(*flag);
+        let colonists: Vec<(Entity, Caste)> = drones
+            .iter()
+            .filter(|(drone, colonist, _)| colonist.nest == entity && !deaths.dead(*drone))
+            .map(|(drone, _, caste)| (drone, *caste))
+            .collect();
+
+        if hibernating.is_some() {
+            let reserve = needs
+                .kinds()
+                .all(|kind| base.food[kind] >= needs[kind] * config.wake_reserve);
+            if cheat.0 || reserve {
+                commands.entity(entity).remove::<Hibernating>();
+                info!("colony {:?} woke from hibernation", flag);
+            } else if !colonists.is_empty() {
+                // starvation takes drones, and whatever they were carrying
+                let shortfall = needs
+                    .kinds()
+                    .map(|kind| {
+                        1.0 - base.food[kind] as f32 / (needs[kind] * config.wake_reserve) as f32
+                    })
+                    .fold(0.0, f32::max);
+                let starved = config.starved(colonists.len(), shortfall);
+                for (drone, _) in colonists.iter().take(starved) {
+                    deaths.kill(*drone);
+                }
             } else {
-                if !cheat.0{
-                // first, kill the colony, all bees will get stuck in 'ToHome'
+                // nothing left to rescue. First, kill the nest
+                ledger.retile(forage.on_tile(map[pos.0]), 0);
                 map[pos.0] = Flag::EMPTY;
+                ledger.abandoned += base.food.total();
                 commands.entity(entity).despawn_recursive();
+                info!("nest of colony {:?} at {} died out", flag, pos.0);
+                // the colony lives on as long as it has another nest, counting the ones that died
+                // earlier this update
+                let left = nests.entry(*flag).or_default();
+                *left = left.saturating_sub(1);
+                if *left > 0 {
+                    continue;
+                }
                 // second, stop the clock
                 game_timer.0.set_repeating(false);
                 // third, send game over message
                 info!("game over!");
-                }
+                game_over_text(&mut commands, &asset_server);
+            }
+            continue;
+        }
 
-            commands
-                .spawn_bundle(TextBundle {
-                    style: Style {
-                        align_self: AlignSelf::FlexEnd,
-                        position_type: PositionType::Absolute,
-                        position: Rect {
-                            bottom: Val::Px(110.0),
-                            right: Val::Px(10.0),
-                            ..Default::default()
-                        },
-                        ..Default::default()
-                    },
-                    // Use the `Text::with_section` constructor
-                    text: Text::with_section(
-                        // Accepts a `String` or any type that converts into a `String`, such as `&str`
-                        "GAME OVER!",
-                        TextStyle {
-                            font: asset_server.load("fonts/monogram.ttf"),
-                            font_size: 100.0,
-                            color: Color::WHITE,
-                        },
-                        // Note: You can use `Default::default()` in place of the `TextAlignment`
-                        TextAlignment {
-                            horizontal: HorizontalAlign::Right,
-                            ..Default::default()
-                        },
-                    ),
-                    ..Default::default()
-                });
+        let affordable = needs.kinds().all(|kind| base.food[kind] >= needs[kind]);
+        if !cheat.0 && !affordable {
+            commands.entity(entity).insert(Hibernating);
+            info!("colony {:?} is starving and hibernating", flag);
+            continue;
+        }
+        if colonists.len() >= config.max {
+            continue;
+        }
+        if !cheat.0 {
+            for kind in needs.kinds() {
+                base.food[kind] -= needs[kind];
+                ledger.spent += needs[kind];
             }
+        }
 
-            commands
-                .spawn()
-                .insert(Drone::new())
-                .insert(DroneState::Exploring)
-                .insert(world::Position(pos.0))
-                .insert(Colonist {
-                    home: pos.0 + Vec2::new(0.5, 0.5),
-                })
-                .insert(flag.clone());
+        // a colony short on food raises scouts to find more, one under attack raises guards
+        let mut counts = [0; 3];
+        for (_, caste) in colonists.iter() {
+            counts[*caste as usize] += 1;
         }
+        let hungry = needs
+            .kinds()
+            .any(|kind| base.food[kind] < needs[kind] * config.wake_reserve);
+        let caste = castes.assign(counts, hungry, threats.against(*flag));
+
+        commands
+            .spawn()
+            .insert(Drone::new())
+            .insert(DroneState::Exploring)
+            .insert(caste)
+            .insert(Payload::default())
+            .insert(Memory::default())
+            .insert(Walk::default())
+            .insert(Infection::default())
+            .insert(world::Position(pos.0))
+            .insert(Colonist {
+                home: pos.0 + Vec2::new(0.5, 0.5),
+                nest: entity,
+            })
+            .insert(*flag);
     }
 }
 
 // signal as a separate system to minimize mutable borrows
 pub fn signal_drones(
-    drones: Query<(&DroneState, &world::Position)>,
+    drones: Query<(Entity, &DroneState, &world::Position, &Flag, &Colonist)>,
     mut gather_event: EventWriter<GatherEvent>,
     mut deposit_event: EventWriter<DepositEvent>,
 ) {
-    for (state, pos) in drones.iter() {
+    for (drone, state, pos, colony, colonist) in drones.iter() {
         match *state {
-            DroneState::Gathering => gather_event.send(GatherEvent(pos.0)),
-            DroneState::Depositing => deposit_event.send(DepositEvent(pos.0)),
+            DroneState::Gathering => gather_event.send(GatherEvent {
+                drone,
+                colony: *colony,
+                pos: pos.0,
+            }),
+            DroneState::Depositing => deposit_event.send(DepositEvent {
+                drone,
+                colony: *colony,
+                nest: colonist.nest,
+            }),
             _ => {}
         }
     }
@@ -311,150 +540,186 @@ pub fn update_drones(
     time_step: Res<world::TimeStep>,
     map: Res<WorldMap>,
     config: Res<Config>,
+    fields: Res<field_systems::Config>,
+    behavior: Res<Behavior>,
+    castes: Res<caste::Config>,
+    explore: Res<explore::Config>,
+    alarm: Res<alarm::Config>,
+    disease: Res<disease::Config>,
+    neighbors: Res<neighbors::Config>,
+    hash: Res<SpatialHash>,
+    threats: Res<Threats>,
+    forage: Res<Forage>,
     food_field: Query<&ScalarField, With<field_systems::Food>>,
-    wall_field: Query<&ScalarField, With<field_systems::Wall>>,
     attractor_field: Query<&VectorField, With<field_systems::Attractor>>,
     repellent_field: Query<&VectorField, With<field_systems::Repellent>>,
     density_field: Query<&ScalarField, With<field_systems::Density>>,
+    discovery_field: Query<&ScalarField, With<field_systems::Discovery>>,
+    alarm_field: Query<&ScalarField, With<field_systems::Alarm>>,
+    queens: Query<&Queen>,
     mut drones: Query<(
+        Entity,
         &mut Drone,
         &mut DroneState,
+        &Payload,
         &mut world::Position,
         &Flag,
         &Colonist,
+        &Caste,
+        &mut Memory,
+        &mut Walk,
+        &Infection,
     )>,
 ) {
     let time_step = time_step.into_inner();
     let food_f = food_field.single();
-    let wall_f = wall_field.single();
     let density_f = density_field.single();
     let attractor_f = attractor_field.single();
     let repellent_f = repellent_field.single();
+    let discovery_f = discovery_field.single();
+    let alarm_f = alarm_field.single();
 
     drones.par_for_each_mut(
         &pool,
         32,
-        |(mut drone, mut state, mut pos, colony, colonist)| {
+        |(entity, mut drone, mut state, payload, mut pos, colony, colonist, caste, mut memory, mut walk, infection)| {
             let colony = *colony;
+            let traits = castes.traits(*caste);
+            // parasites slow a drone down
+            let speed = traits.move_speed * infection.speed(&disease);
+            memory.integrate(pos.0, colonist.home);
             //let (entity, mut drone) = q;
             let mut rng = SmallRng::from_entropy();
 
+            // a wall that sprang up under the drone pushes it out
+            let escaped = map.escape(pos.0);
+            if escaped != pos.0 {
+                pos.0 = escaped;
+            }
             let cell = match map.get_vec2(pos.0) {
                 Some(f) => f,
                 None => Flag::EMPTY,
             };
 
-            // stuck-in-wall bounce
-            if cell.intersects(Flag::WALL) {
-                let bounce_direction = -drone.direction;
+            // only tiles yielding something the colony eats count as food
+            let food = traits.forages && forage.edible(cell, colony, payload.resource);
+            let full = payload.amount >= config.capacity;
 
-                pos.0 += bounce_direction * *time_step;
-                let cell = map[pos.0]; // current cell
-                if cell.intersects(Flag::WALL) {
-                    pos.0 += bounce_direction * *time_step * 10.0;
-                    // early return, try again next frame
-                    return
+            // remember the patch food was last gathered from, and forget it once it's found depleted
+            if *state == DroneState::Gathering && food {
+                memory.patch = Some(pos.0.floor() + Vec2::new(0.5, 0.5));
+                memory.richness = fields.richness(cell, &forage);
+            }
+            if let Some(patch) = memory.patch {
+                let depleted = !food && pos.0.distance(patch) < 1.0;
+                if depleted && matches!(*state, DroneState::ToFood | DroneState::Gathering) {
+                    memory.patch = None;
                 }
             }
 
-            // state change
-            *state = match *state {
-                DroneState::ToHome if cell.intersects(Flag::COLONY_ALL) => DroneState::Depositing,
-                DroneState::ToHome if !cell.intersects(Flag::COLONY_ALL) => DroneState::ToHome,
-                DroneState::ToHomeNoFood if cell.intersects(Flag::COLONY_ALL) => {
-                    DroneState::Resting
-                }
-                DroneState::ToHomeNoFood if !cell.intersects(colony) => DroneState::ToHomeNoFood,
-                DroneState::ToFood if cell.intersects(Flag::HIVE_FOOD) => DroneState::Gathering,
-                DroneState::ToFood if !cell.intersects(Flag::HIVE_FOOD) && drone.autonomy => {
-                    DroneState::Exploring
-                }
-                DroneState::Exploring if !drone.autonomy => DroneState::ToFood,
-                DroneState::Exploring if drone.autonomy => DroneState::Exploring,
-                DroneState::Gathering if cell.intersects(Flag::HIVE_FOOD) => DroneState::ToHome,
-                DroneState::Gathering if !cell.intersects(Flag::HIVE_FOOD) => {
-                    DroneState::ToHomeNoFood
-                } // food source ran out
-                DroneState::Depositing => DroneState::ToFood,
-                DroneState::Resting => DroneState::ToFood,
-                DroneState::Dead => DroneState::Dead, // death is a special state, only entered by calling kill
-                _ => *state,
+            // state change, as laid out in the behavior file
+            let facts = Facts {
+                at_home: cell.intersects(colony),
+                at_nest: cell.intersects(Flag::COLONY_ALL),
+                food,
+                full,
+                carrying: payload.amount > 0,
+                autonomous: drone.autonomy,
+                // guards head for trouble rather than away from it
+                danger: traits.forages && alarm_f.grad(pos.0).length() > alarm.danger,
             };
-
-            // pick which signals (if any) the drone cares about
-            let local_density = density_f.grad(pos.0) * 0.05;
-            let food_gradient = food_f.grad(pos.0);
-            let signal = match *state {
-                DroneState::ToHome => Some((colonist.home - pos.0 - local_density) * 5.0),
-                DroneState::ToHomeNoFood => Some((colonist.home - pos.0 - local_density) * 5.0),
-                DroneState::ToFood => {
-                    Some(food_gradient + attractor_f[pos.0] - repellent_f[pos.0] - local_density)
+            *state = behavior.next(*state, &facts);
+            walk.track(*state, pos.0.as_ivec2(), f32::from(time_step));
+
+            // drones right around this one, a nestmate brushing antennae may pass on where it
+            // found food
+            let sensed = Sensed::new(&hash, &neighbors, entity, pos.0, colony, colonist.home);
+            if let (None, Some(patch)) = (memory.patch, sensed.patch) {
+                if rng.gen_bool(neighbors.share_chance) {
+                    memory.patch = Some(patch);
                 }
-                DroneState::Exploring => Some(food_gradient - local_density),
-                DroneState::Gathering => None,
-                DroneState::Depositing => None,
-                DroneState::Resting => None,
-                DroneState::Dead => None,
-            };
+            }
 
-            // early exit if no signal
-            if let Some(mut signal) = signal {
-                // Determine the drones current autonomy based on signals they care about
-                // non signals translate to no autonomy
-                drone.autonomy = match *state {
-                    DroneState::ToFood => {
-                        if signal.length() < config.explore_threshold {
-                            true
-                        } else {
-                            false
-                        }
-                    }
-                    DroneState::Exploring => {
-                        if food_gradient.length() > config.explore_threshold
-                            || attractor_f[pos.0].length() > config.explore_threshold
-                        {
-                            false
-                        } else {
-                            true
-                        }
+            // what the drone senses, pheromone trails weighted by how closely its caste follows them
+            let sense = |term: Term| match term {
+                Term::Home => memory.home,
+                Term::Memory => memory
+                    .patch
+                    .map_or(Vec2::ZERO, |patch| (patch - pos.0).normalize_or_zero()),
+                Term::Food => food_f.grad(pos.0),
+                Term::Attractor => attractor_f[pos.0] * traits.trail,
+                Term::Repellent => repellent_f[pos.0],
+                Term::Density => density_f.grad(pos.0),
+                Term::Discovery => discovery_f.grad(pos.0) * traits.trail,
+                Term::Separation => sensed.separation,
+                Term::Alignment => sensed.alignment,
+                Term::Congestion => sensed.congestion,
+                Term::Alarm => alarm_f.grad(pos.0),
+                Term::Signal => Vec2::ZERO,
+            };
+            // castes that don't forage patrol around home instead of looking for food, answering
+            // threats near home and otherwise circling back whenever they stray
+            let patrol = !traits.forages
+                && matches!(*state, DroneState::ToFood | DroneState::Exploring);
+            let signal = if patrol {
+                let heading = match threats.near(colony, colonist.home, castes.patrol_radius) {
+                    Some(threat) => threat - pos.0,
+                    None if pos.0.distance(colonist.home) > castes.patrol_radius => {
+                        colonist.home - pos.0
                     }
-                    _ => false,
+                    None => Vec2::ZERO,
                 };
+                Some(heading - sense(Term::Density) * 0.05)
+            } else {
+                behavior.signal(*state, sense)
+            };
 
-                // Determine where the drone should go next based on signals
-                signal.x += rng.gen_range(-config.chaos..config.chaos);
-                signal.y += rng.gen_range(-config.chaos..config.chaos);
+            // early exit if no signal
+            if let Some(mut signal) = signal {
+                // Determine the drones current autonomy based on signals they care about, guards
+                // never settle on a trail
+                // the nest's queen decides how adventurous its drones are
+                let threshold = traits.explore_threshold
+                    * queens.get(colonist.nest).map_or(1.0, |queen| queen.explore_bias);
+                drone.autonomy =
+                    patrol || behavior.autonomous(*state, signal, threshold, sense);
+
+                // Determine where the drone should go next based on signals, exploring drones
+                // wander the way their colony explores
+                if *state == DroneState::Exploring && !patrol {
+                    signal += walk.wander(
+                        explore.strategy(colony),
+                        &explore,
+                        drone.direction,
+                        traits.chaos,
+                        f32::from(time_step),
+                        &mut rng,
+                    );
+                } else {
+                    signal.x += rng.gen_range(-traits.chaos..traits.chaos);
+                    signal.y += rng.gen_range(-traits.chaos..traits.chaos);
+                }
                 signal = signal.normalize_or_zero();
 
-                let candidate_direction = drone
+                drone.direction = drone
                     .direction
-                    .lerp(signal, config.turn_speed)
+                    .lerp(signal, traits.turn_speed)
                     .normalize_or_zero()
-                    * config.move_speed;
-
-                // look ahead to consider walls
-                let candidate_pos = pos.0 + (*time_step * candidate_direction);
-                // FIXME: lot of unneccessary computation here
-                drone.direction = candidate_direction
-                    .lerp(-0.5 * wall_f.grad(candidate_pos), config.turn_speed)
-                    .normalize_or_zero()
-                    * config.move_speed;
-
-                let new_pos = pos.0 + (*time_step * drone.direction);
-
-                // stop drone in its tracks if its at the edge of a wall, that way it can cleanly
-                // 'bounce' off
-                pos.0 = match map.get_vec2(new_pos) {
-                    Some(flag) if flag.intersects(Flag::WALL) => {
-                        // if you hit a wall, turn around
-                        drone.direction *= -1.0;
-                        pos.0 + (*time_step * drone.direction)
-                    }
-                    Some(flag) if !flag.intersects(Flag::WALL) => new_pos,
-                    None => pos.0,
-                    _ => pos.0, // should be impossible to reach
+                    * speed;
+
+                // sweep against walls, sliding along any in the way and heading on the way it
+                // actually went. A drone wedged in a corner turns around
+                let motion = *time_step * drone.direction;
+                let new_pos = map.sweep(pos.0, motion);
+                if new_pos != pos.0 + motion {
+                    let heading = (new_pos - pos.0).normalize_or_zero();
+                    drone.direction = if heading == Vec2::ZERO {
+                        -drone.direction
+                    } else {
+                        heading * speed
+                    };
                 }
-            } else {
+                pos.0 = new_pos;
             }
         },
     );
@@ -480,3 +745,20 @@ pub fn update_drone_sprites(
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starvation_scales_with_colony_and_shortfall() {
+        let config = Config::default();
+        assert_eq!(config.starved(0, 1.0), 0);
+        assert_eq!(config.starved(3, 0.0), 0);
+        // a lone drone still starves
+        assert_eq!(config.starved(1, 0.1), 1);
+        assert!(config.starved(1000, 1.0) > config.starved(100, 1.0));
+        assert!(config.starved(1000, 1.0) > config.starved(1000, 0.2));
+        assert!(config.starved(1000, 1.0) < 1000);
+    }
+}
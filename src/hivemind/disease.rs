@@ -0,0 +1,369 @@
+/// Parasites. Some flowers carry them, and drones gathering there pick them up and pass them on to
+/// the drones they run into, out in the field or crowding the nest entrance. A parasite load grows
+/// until it kills its drone, slowing it down on the way. Nestmates groom it back down, unless the
+/// colony has quarantined its sick to stop the spread at the nest
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    colony::{Base, Colonist, DroneState},
+    neighbors::{self, Neighbor, SpatialHash},
+    Deaths, Drone,
+};
+use crate::world::{self, Flag, WorldMap};
+
+#[derive(Debug)]
+pub struct Config {
+    /// share of flower tiles carrying the parasite
+    pub infested: f64,
+    /// chance a drone picks the parasite up each update it gathers on an infested flower
+    pub flower_chance: f64,
+    /// chance a sick drone passes it on to each drone it touches, each update
+    pub contact_chance: f64,
+    /// chance a healthy drone at the nest entrance catches it, per sick nestmate there
+    pub nest_chance: f64,
+    /// parasite load a drone starts out with
+    pub dose: f32,
+    /// load gained per second
+    pub growth: f32,
+    /// load that kills a drone
+    pub lethal: f32,
+    /// how much of its speed a drone near a lethal load loses
+    pub slowdown: f32,
+    /// load each healthy nestmate grooms off a sick drone at the nest, per second
+    pub grooming: f32,
+    /// most nestmates that can groom a drone at once
+    pub groomers: usize,
+    /// share of sick drones at which a nest quarantines them
+    pub quarantine: f32,
+    /// the real-time period between colonies sampling their health
+    pub clock: f32,
+}
+
+impl Config {
+    pub fn default() -> Self {
+        Self {
+            infested: 0.2,
+            flower_chance: 0.01,
+            contact_chance: 0.02,
+            nest_chance: 0.005,
+            dose: 1.0,
+            growth: 1.0,
+            lethal: 120.0,
+            slowdown: 0.5,
+            grooming: 0.5,
+            groomers: 4,
+            quarantine: 0.25,
+            clock: 5.0,
+        }
+    }
+}
+
+/// Time until colonies next sample their health
+pub struct EpidemicClock(pub Timer);
+
+/// How many parasites a drone carries, a healthy drone carries none
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct Infection {
+    pub load: f32,
+}
+
+impl Infection {
+    pub fn sick(&self) -> bool {
+        self.load > 0.0
+    }
+
+    /// Grow the load over `dt` seconds while `groomers` nestmates groom it down
+    pub fn progress(&mut self, config: &Config, dt: f32, groomers: usize) {
+        let grooming = config.grooming * groomers.min(config.groomers) as f32;
+        self.load = (self.load + (config.growth - grooming) * dt).max(0.0);
+    }
+
+    /// How much of its speed the drone keeps
+    pub fn speed(&self, config: &Config) -> f32 {
+        1.0 - config.slowdown * (self.load / config.lethal).min(1.0)
+    }
+}
+
+/// A nest keeping its sick drones away from the entrance. They stop infecting nestmates there, but
+/// go ungroomed
+#[derive(Component)]
+pub struct Quarantined;
+
+/// Flower tiles carrying the parasite, decided the first time a drone gathers on each
+#[derive(Default)]
+pub struct Blight(HashMap<IVec2, bool>);
+
+impl Blight {
+    fn infested(&mut self, tile: IVec2, config: &Config, rng: &mut SmallRng) -> bool {
+        *self.0.entry(tile).or_insert_with(|| rng.gen_bool(config.infested))
+    }
+}
+
+/// A colony's health at one point in time
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// seconds since the first sample
+    pub time: f32,
+    pub drones: usize,
+    pub sick: usize,
+    /// mean load of the sick
+    pub load: f32,
+    pub quarantined: bool,
+}
+
+/// A colony's infection curve and running totals
+#[derive(Debug, Default)]
+pub struct Curve {
+    pub samples: Vec<Sample>,
+    pub infections: u32,
+    pub deaths: u32,
+    pub cures: u32,
+}
+
+#[derive(Default)]
+pub struct Epidemic {
+    elapsed: f32,
+    pub curves: HashMap<Flag, Curve>,
+}
+
+/// Chance a healthy drone catches the parasite this update, from `touching` sick drones touching
+/// it and `crowding` sick nestmates crowding the nest entrance it's at
+fn exposure(config: &Config, touching: i32, crowding: i32) -> f64 {
+    1.0 - (1.0 - config.contact_chance).powi(touching) * (1.0 - config.nest_chance).powi(crowding)
+}
+
+/// Whether a sick drone passes the parasite on to another it touches. The `isolated` sick of a
+/// quarantined nest keep clear of their nestmates around its entrance
+fn contagious(isolated: bool, colony: Flag, other: &Neighbor) -> bool {
+    !isolated || other.colony != colony
+}
+
+/// Drones catch the parasite on infested flowers, from sick drones they touch, and from sick
+/// nestmates crowding the entrance of their nest. A quarantined nest's sick stay clear of
+/// nestmates at its entrance
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn spread(
+    map: Res<WorldMap>,
+    config: Res<Config>,
+    neighbors: Res<neighbors::Config>,
+    hash: Res<SpatialHash>,
+    mut blight: ResMut<Blight>,
+    mut epidemic: ResMut<Epidemic>,
+    quarantined: Query<&Quarantined>,
+    mut drones: Query<
+        (Entity, &DroneState, &world::Position, &Flag, &Colonist, &mut Infection),
+        With<Drone>,
+    >,
+) {
+    let mut rng = SmallRng::from_entropy();
+    let mut caught = HashSet::new();
+    let mut touching: HashMap<Entity, i32> = HashMap::new();
+    let mut sick_at_nest: HashMap<Entity, i32> = HashMap::new();
+
+    for (entity, state, pos, colony, colonist, infection) in drones.iter() {
+        if infection.sick() {
+            let at_entrance = pos.0.distance(colonist.home) <= neighbors.entrance;
+            let isolated = at_entrance && quarantined.get(colonist.nest).is_ok();
+            for n in hash.near(entity, pos.0, neighbors.radius) {
+                if contagious(isolated, *colony, n) {
+                    *touching.entry(n.drone).or_default() += 1;
+                }
+            }
+            if at_entrance && !isolated {
+                *sick_at_nest.entry(colonist.nest).or_default() += 1;
+            }
+        } else if *state == DroneState::Gathering
+            && map.get_vec2(pos.0).map_or(false, |cell| cell.intersects(Flag::FLOWER))
+            && blight.infested(pos.0.as_ivec2(), &config, &mut rng)
+            && rng.gen_bool(config.flower_chance)
+        {
+            caught.insert(entity);
+        }
+    }
+
+    for (entity, _, pos, colony, colonist, mut infection) in drones.iter_mut() {
+        if infection.sick() {
+            continue;
+        }
+        let crowding = match sick_at_nest.get(&colonist.nest) {
+            Some(sick) if pos.0.distance(colonist.home) <= neighbors.entrance => *sick,
+            _ => 0,
+        };
+        let touched = touching.get(&entity).copied().unwrap_or(0);
+        if caught.contains(&entity) || rng.gen_bool(exposure(&config, touched, crowding)) {
+            infection.load = config.dose;
+            epidemic.curves.entry(*colony).or_default().infections += 1;
+        }
+    }
+}
+
+/// Parasite loads grow, groomed down by healthy nestmates at the nest. A drone whose load clears
+/// is cured, one whose load turns lethal dies with whatever it was carrying
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn progress(
+    time_step: Res<world::TimeStep>,
+    config: Res<Config>,
+    neighbors: Res<neighbors::Config>,
+    hash: Res<SpatialHash>,
+    mut deaths: ResMut<Deaths>,
+    mut epidemic: ResMut<Epidemic>,
+    quarantined: Query<&Quarantined>,
+    mut drones: Query<(Entity, &world::Position, &Flag, &Colonist, &mut Infection), With<Drone>>,
+) {
+    let dt = f32::from(time_step.into_inner());
+    let sick: HashSet<Entity> = drones
+        .iter()
+        .filter(|(_, _, _, _, infection)| infection.sick())
+        .map(|(entity, _, _, _, _)| entity)
+        .collect();
+
+    for (entity, pos, colony, colonist, mut infection) in drones.iter_mut() {
+        if !infection.sick() || deaths.dead(entity) {
+            continue;
+        }
+        let groomers = if pos.0.distance(colonist.home) <= neighbors.entrance
+            && quarantined.get(colonist.nest).is_err()
+        {
+            hash.near(entity, pos.0, neighbors.radius)
+                .filter(|n| n.colony == *colony && !sick.contains(&n.drone))
+                .count()
+        } else {
+            0
+        };
+        infection.progress(&config, dt, groomers);
+
+        let curve = epidemic.curves.entry(*colony).or_default();
+        if !infection.sick() {
+            curve.cures += 1;
+        } else if infection.load >= config.lethal && deaths.kill(entity) {
+            curve.deaths += 1;
+        }
+    }
+}
+
+/// Every so often each nest samples its health, quarantining its sick once too many of its drones
+/// are, and lifting the quarantine once few are
+pub fn respond(
+    mut commands: Commands,
+    time_step: Res<world::TimeStep>,
+    config: Res<Config>,
+    mut clock: ResMut<EpidemicClock>,
+    mut epidemic: ResMut<Epidemic>,
+    nests: Query<(Entity, &Base, Option<&Quarantined>)>,
+    drones: Query<(&Colonist, &Infection), With<Drone>>,
+) {
+    if !clock.0.tick(time_step.into_inner().into()).just_finished() {
+        return;
+    }
+    epidemic.elapsed += config.clock;
+    let time = epidemic.elapsed;
+
+    let mut health: HashMap<Flag, Sample> = HashMap::new();
+    for (nest, base, quarantined) in nests.iter() {
+        let (mut population, mut sick, mut load) = (0, 0, 0.0);
+        for (colonist, infection) in drones.iter() {
+            if colonist.nest == nest {
+                population += 1;
+                if infection.sick() {
+                    sick += 1;
+                    load += infection.load;
+                }
+            }
+        }
+
+        let prevalence = sick as f32 / population.max(1) as f32;
+        let quarantine = prevalence >= config.quarantine;
+        if quarantine && quarantined.is_none() {
+            commands.entity(nest).insert(Quarantined);
+            info!(
+                "colony {:?} quarantined its sick, {}/{} drones infected",
+                base.colony, sick, population
+            );
+        } else if !quarantine && quarantined.is_some() {
+            commands.entity(nest).remove::<Quarantined>();
+            info!("colony {:?} lifted its quarantine", base.colony);
+        }
+
+        // nests of the same colony add up into a single curve
+        let sample = health.entry(base.colony).or_insert(Sample {
+            time,
+            drones: 0,
+            sick: 0,
+            load: 0.0,
+            quarantined: false,
+        });
+        sample.drones += population;
+        sample.sick += sick;
+        sample.load += load;
+        sample.quarantined |= quarantine;
+    }
+
+    for (colony, mut sample) in health {
+        sample.load /= sample.sick.max(1) as f32;
+        let curve = epidemic.curves.entry(colony).or_default();
+        curve.samples.push(sample);
+        debug!(
+            "colony {:?} at {:.0}s: {}/{} drones sick, mean load {:.1}, {} infections, {} deaths, \
+             {} cures so far",
+            colony,
+            sample.time,
+            sample.sick,
+            sample.drones,
+            sample.load,
+            curve.infections,
+            curve.deaths,
+            curve.cures
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grooming_clears_what_would_kill() {
+        let config = Config::default();
+        let mut ungroomed = Infection { load: config.dose };
+        let mut groomed = ungroomed;
+        for _ in 0..(config.lethal / config.growth) as usize + 1 {
+            ungroomed.progress(&config, 1.0, 0);
+            groomed.progress(&config, 1.0, config.groomers);
+        }
+        assert!(ungroomed.load >= config.lethal);
+        assert!(!groomed.sick());
+        assert!(ungroomed.speed(&config) < Infection::default().speed(&config));
+    }
+
+    #[test]
+    fn quarantine_shields_nestmates_at_the_entrance() {
+        let config = Config::default();
+        let sick: Vec<Neighbor> = (0..3)
+            .map(|id| Neighbor {
+                drone: Entity::from_raw(id),
+                pos: Vec2::ZERO,
+                direction: Vec2::ZERO,
+                colony: Flag::COLONY_Y,
+                patch: None,
+            })
+            .collect();
+        let rival = Neighbor {
+            colony: Flag::COLONY_M,
+            ..sick[0]
+        };
+
+        // a healthy drone at the entrance, touched by its three sick nestmates crowding it
+        let touching =
+            |isolated| sick.iter().filter(|n| contagious(isolated, Flag::COLONY_Y, n)).count();
+        let open = exposure(&config, touching(false) as i32, 3);
+        let quarantined = exposure(&config, touching(true) as i32, 0);
+        assert!(open > 0.0);
+        assert_eq!(quarantined, 0.0);
+
+        // quarantine doesn't keep the sick from rivals
+        assert!(contagious(true, Flag::COLONY_Y, &rival));
+    }
+}
@@ -0,0 +1,188 @@
+/// Exploration strategies. How an exploring drone picks its way when no signal is leading it,
+/// chosen per colony, along with how quickly each strategy turns up food nobody in the colony had
+/// gathered from before
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::{PI, TAU};
+
+use super::colony::DroneState;
+use crate::world::Flag;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// uniform noise around the signal, diffusing out from the nest
+    Diffusive,
+    /// straight legs in random directions, most short and a few very long
+    Levy,
+    /// small random turns off the current heading
+    Correlated,
+    /// an outward spiral from wherever exploring began
+    Spiral,
+}
+
+#[derive(Debug)]
+pub struct Config {
+    /// strategy of each colony, colonies not listed use `fallback`
+    pub strategies: Vec<(Flag, Strategy)>,
+    pub fallback: Strategy,
+    /// power law exponent of Lévy leg lengths, between 1 and 3
+    pub levy_exponent: f32,
+    /// bounds on Lévy leg lengths, in seconds
+    pub levy_min: f32,
+    pub levy_max: f32,
+    /// widest turn a correlated walk takes in one update, as a share of half a turn
+    pub correlated_turn: f32,
+    /// how fast a spiral turns, it slows as the spiral widens
+    pub spiral_turn: f32,
+}
+
+impl Config {
+    pub fn default() -> Self {
+        Self {
+            strategies: vec![
+                (Flag::COLONY_Y, Strategy::Levy),
+                (Flag::COLONY_M, Strategy::Correlated),
+                (Flag::COLONY_C, Strategy::Spiral),
+            ],
+            fallback: Strategy::Diffusive,
+            levy_exponent: 2.0,
+            levy_min: 0.5,
+            levy_max: 30.0,
+            correlated_turn: 0.1,
+            spiral_turn: 4.0,
+        }
+    }
+
+    pub fn strategy(&self, colony: Flag) -> Strategy {
+        self.strategies
+            .iter()
+            .find(|(c, _)| *c == colony)
+            .map_or(self.fallback, |(_, strategy)| *strategy)
+    }
+}
+
+/// Where a drone is in its exploration walk, and how long it has been searching for food
+#[derive(Component, Default)]
+pub struct Walk {
+    /// radians
+    heading: f32,
+    /// seconds left on the current Lévy leg
+    leg: f32,
+    /// seconds into the current spiral
+    spiral: f32,
+    /// seconds since the drone set out looking for food, None while it isn't
+    search: Option<f32>,
+    /// how long the last search took and the tile it ended on, until recorded
+    pub found: Option<(f32, IVec2)>,
+}
+
+impl Walk {
+    /// Noise an exploring drone adds to its signal
+    pub fn wander(
+        &mut self,
+        strategy: Strategy,
+        config: &Config,
+        direction: Vec2,
+        chaos: f32,
+        dt: f32,
+        rng: &mut impl Rng,
+    ) -> Vec2 {
+        match strategy {
+            Strategy::Diffusive => {
+                return Vec2::new(rng.gen_range(-chaos..chaos), rng.gen_range(-chaos..chaos))
+            }
+            Strategy::Levy => {
+                self.leg -= dt;
+                if self.leg <= 0.0 {
+                    self.heading = rng.gen_range(0.0..TAU);
+                    // inverse transform sampling of a power law
+                    let u: f32 = rng.gen_range(f32::EPSILON..1.0);
+                    self.leg = (config.levy_min * u.powf(-1.0 / (config.levy_exponent - 1.0)))
+                        .min(config.levy_max);
+                }
+            }
+            Strategy::Correlated => {
+                let turn = config.correlated_turn * PI;
+                self.heading = direction.y.atan2(direction.x) + rng.gen_range(-turn..=turn);
+            }
+            Strategy::Spiral => {
+                if self.spiral == 0.0 {
+                    self.heading = direction.y.atan2(direction.x);
+                }
+                self.spiral += dt;
+                self.heading += config.spiral_turn * dt / (1.0 + self.spiral);
+            }
+        }
+        Vec2::new(self.heading.cos(), self.heading.sin()) * chaos
+    }
+
+    /// Time the drone's search, a search starts when it sets out exploring and ends when it
+    /// gathers
+    pub fn track(&mut self, state: DroneState, tile: IVec2, dt: f32) {
+        match state {
+            DroneState::Exploring => {
+                *self.search.get_or_insert(0.0) += dt;
+            }
+            DroneState::ToFood => {
+                if let Some(search) = self.search.as_mut() {
+                    *search += dt;
+                }
+            }
+            DroneState::Gathering => {
+                if let Some(search) = self.search.take() {
+                    self.found = Some((search, tile));
+                }
+            }
+            _ => self.search = None,
+        }
+        if state != DroneState::Exploring {
+            self.leg = 0.0;
+            self.spiral = 0.0;
+        }
+    }
+}
+
+/// Searches that ended on a food tile the colony hadn't gathered from before, per strategy
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Record {
+    pub discoveries: u32,
+    /// seconds spent on those searches
+    pub searching: f32,
+}
+
+#[derive(Default)]
+pub struct Discoveries {
+    /// food tiles each colony has gathered from
+    known: HashSet<(Flag, IVec2)>,
+    pub records: HashMap<Strategy, Record>,
+}
+
+/// Record how long every finished search took, counting only finds of food new to the colony
+pub fn record(
+    config: Res<Config>,
+    mut discoveries: ResMut<Discoveries>,
+    mut walks: Query<(&mut Walk, &Flag)>,
+) {
+    for (mut walk, colony) in walks.iter_mut() {
+        let (search, tile) = match walk.found.take() {
+            Some(found) => found,
+            None => continue,
+        };
+        if !discoveries.known.insert((*colony, tile)) {
+            continue;
+        }
+        let strategy = config.strategy(*colony);
+        let record = discoveries.records.entry(strategy).or_default();
+        record.discoveries += 1;
+        record.searching += search;
+        debug!(
+            "{:?} search found food at {} in {:.1}s, {:.1}s on average over {} finds",
+            strategy,
+            tile,
+            search,
+            record.searching / record.discoveries as f32,
+            record.discoveries
+        );
+    }
+}
@@ -1,10 +1,48 @@
 use crate::{
-    hivemind::{colony::DroneState, Drone, ScalarField, VectorField},
-    world::{self, WorldMap},
+    hivemind::{
+        caste::{self, Caste},
+        colony::{self, Colonist, DroneState, Memory},
+        Drone, Forage, Payload, ScalarField, VectorField,
+    },
+    world::{self, Flag, WorldMap},
 };
 /// Specific field implementations used by the hivemind
 use bevy::prelude::*;
 
+#[derive(Debug)]
+pub struct Config {
+    /// food pheromone a full tile gives off every update
+    pub food_emission: f32,
+    /// tile quantity that counts as a full patch
+    pub rich: u32,
+    /// trip length in tiles at which trails are laid at half strength
+    pub trip_scale: f32,
+}
+
+impl Config {
+    pub fn default() -> Self {
+        Self {
+            food_emission: 100.0,
+            rich: world::Flower::MAX,
+            trip_scale: 20.0,
+        }
+    }
+
+    /// How much of a full patch is left on a tile, tiles that never deplete always count as full
+    pub fn richness(&self, cell: Flag, forage: &Forage) -> f32 {
+        if !forage.depletes(cell) {
+            return 1.0;
+        }
+        (cell.get_resource_quantity() as f32 / self.rich as f32).min(1.0)
+    }
+
+    /// Strength of the trail a drone lays on its way home, stronger for fuller payloads of food the
+    /// colony needs more, from richer patches and shorter trips
+    pub fn trail(&self, load: f32, quality: f32, richness: f32, trip: f32) -> f32 {
+        load * quality * richness * self.trip_scale / (self.trip_scale + trip)
+    }
+}
+
 #[derive(Component)]
 pub struct Food;
 
@@ -12,12 +50,15 @@ pub struct Food;
 pub struct Wall;
 
 pub fn update_world(
+    config: Res<Config>,
     mut food_field: Query<&mut ScalarField, With<Food>>,
     mut wall_field: Query<&mut ScalarField, (With<Wall>, Without<Food>)>,
     mut attractor_field: Query<&mut VectorField, (With<Attractor>, Without<Food>, Without<Wall>)>,
     mut repellent_field: Query<&mut VectorField, (With<Repellent>, Without<Attractor>, Without<Food>, Without<Wall>)>,
     map: Res<WorldMap>,
+    forage: Res<Forage>,
 ) {
+    let food = forage.food();
     let mut wall_field = wall_field.single_mut();
     let mut food_field = food_field.single_mut();
     let mut attractor_field = attractor_field.single_mut();
@@ -25,8 +66,8 @@ pub fn update_world(
 
     for y in 0..map.data.len() {
         for x in 0..map.data.len() {
-            if map[y][x].intersects(world::Flag::HIVE_FOOD) {
-                food_field[y][x] += 100.0;
+            if map[y][x].intersects(food) {
+                food_field[y][x] += config.food_emission * config.richness(map[y][x], &forage);
                 wall_field[y][x] = 0.0;
             }
             if map[y][x].intersects(world::Flag::WALL) {
@@ -44,15 +85,31 @@ pub fn update_world(
 #[derive(Component)]
 pub struct Attractor;
 
+/// Drones heading home with food lay a trail back to where they found it. Drones that don't
+/// remember where that was have no trail to lay
+#[allow(clippy::type_complexity)]
 pub fn update_attractor(
     mut field: Query<&mut VectorField, With<Attractor>>,
-    drones: Query<(&world::Position, &Drone, &DroneState)>,
+    config: Res<Config>,
+    colony_config: Res<colony::Config>,
+    forage: Res<Forage>,
+    drones: Query<(&world::Position, &Drone, &DroneState, &Payload, &Memory, &Colonist, &Flag)>,
 ) {
     let mut field = field.single_mut();
     drones
         .iter()
-        .filter(|(_, _, state)| **state == DroneState::ToHome)
-        .for_each(|(pos, drone, _)| field[pos.0] += -drone.direction);
+        .filter(|(_, _, state, _, _, _, _)| **state == DroneState::ToHome)
+        .for_each(|(pos, drone, _, payload, memory, colonist, colony)| {
+            let (patch, resource) = match (memory.patch, payload.resource) {
+                (Some(patch), Some(resource)) => (patch, resource),
+                _ => return,
+            };
+            let load = payload.amount as f32 / colony_config.capacity as f32;
+            let quality = forage.quality(*colony, resource);
+            let trip = patch.distance(colonist.home);
+            field[pos.0] +=
+                -drone.direction * config.trail(load, quality, memory.richness, trip);
+        });
 }
 
 #[derive(Component)]
@@ -80,6 +137,26 @@ pub fn update_density(
     drones.for_each(|(pos, _)| field[pos.0] += 0.1);
 }
 
+/// Marks where scouts found food, for foragers to follow
+#[derive(Component)]
+pub struct Discovery;
+
+pub fn update_discovery(
+    mut field: Query<&mut ScalarField, With<Discovery>>,
+    config: Res<caste::Config>,
+    drones: Query<(&world::Position, &Caste, &DroneState)>,
+) {
+    let mut field = field.single_mut();
+    drones
+        .iter()
+        .filter(|(_, caste, state)| **caste == Caste::Scout && **state == DroneState::Gathering)
+        .for_each(|(pos, _, _)| field[pos.0] += config.discovery);
+}
+
+/// Raised where drones came to harm, see `alarm`
+#[derive(Component)]
+pub struct Alarm;
+
 pub fn setup(mut commands: Commands) {
     // FIXME: remove debug addition of food to grid
     commands.spawn().insert(ScalarField::default()).insert(Food);
@@ -95,9 +172,33 @@ pub fn setup(mut commands: Commands) {
         .spawn()
         .insert(ScalarField::default())
         .insert(Density);
+    commands
+        .spawn()
+        .insert(ScalarField::default())
+        .insert(Discovery);
+    commands
+        .spawn()
+        .insert(ScalarField::default())
+        .insert(Alarm);
     commands
         .spawn()
         .insert(ScalarField::default_wall())
         .insert(Wall);
     info!("setup colony fields");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rich_full_short_trips_lay_stronger_trails() {
+        let config = Config::default();
+        let rich = config.trail(1.0, 1.0, 1.0, 20.0);
+        assert!(rich > config.trail(1.0, 1.0, 0.05, 20.0));
+        assert!(rich > config.trail(0.3, 1.0, 1.0, 20.0));
+        assert!(rich > config.trail(1.0, 0.5, 1.0, 20.0));
+        assert!(rich > config.trail(1.0, 1.0, 1.0, 60.0));
+        assert!((rich - 0.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,173 @@
+/// Colony merging. Nests of different colonies whose trails keep crossing grow an affinity for
+/// each other, and once it's strong enough they merge into a single combined colony
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    colony::{Base, Colonist, DroneState},
+    swarm::Scout,
+    Drone,
+};
+use crate::world::{self, Flag, WorldMap};
+
+#[derive(Debug)]
+pub struct Config {
+    /// the real-time period between merge updates
+    pub merge_clock: f32,
+    /// tiles both nests need trails through in a single update to count as overlapping
+    pub contact_tiles: u32,
+    /// seconds of overlap before two nests merge
+    pub merge_time: f32,
+}
+
+impl Config {
+    pub fn default() -> Self {
+        Self {
+            merge_clock: 2.0,
+            contact_tiles: 4,
+            merge_time: 60.0,
+        }
+    }
+}
+
+/// Time until merges are next considered
+pub struct MergeClock(pub Timer);
+
+/// Seconds of overlap built up between pairs of nests, pairs are kept in entity order
+#[derive(Default)]
+pub struct Affinity(pub HashMap<(Entity, Entity), f32>);
+
+fn pair(a: Entity, b: Entity) -> (Entity, Entity) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Track which nests share trails and merge those that have overlapped for long enough
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn merge(
+    mut commands: Commands,
+    mut map: ResMut<WorldMap>,
+    time_step: Res<world::TimeStep>,
+    config: Res<Config>,
+    mut clock: ResMut<MergeClock>,
+    mut affinity: ResMut<Affinity>,
+    mut bases: Query<(&mut Base, &mut Flag, &world::Position), With<world::Colony>>,
+    mut drones: Query<
+        (
+            &mut Colonist,
+            &mut Flag,
+            &DroneState,
+            &world::Position,
+            Option<&mut Scout>,
+        ),
+        (With<Drone>, Without<world::Colony>),
+    >,
+) {
+    if !clock.0.tick(time_step.into_inner().into()).just_finished() {
+        return;
+    }
+
+    // nests whose drones are laying trails through each tile
+    let mut trails: HashMap<IVec2, Vec<Entity>> = HashMap::new();
+    for (colonist, _, state, pos, _) in drones.iter() {
+        if matches!(*state, DroneState::ToHome | DroneState::ToFood) {
+            let nests = trails.entry(pos.0.as_ivec2()).or_default();
+            if !nests.contains(&colonist.nest) {
+                nests.push(colonist.nest);
+            }
+        }
+    }
+    let mut contacts: HashMap<(Entity, Entity), u32> = HashMap::new();
+    for nests in trails.values() {
+        for (i, a) in nests.iter().enumerate() {
+            for b in &nests[i + 1..] {
+                *contacts.entry(pair(*a, *b)).or_default() += 1;
+            }
+        }
+    }
+
+    // only nests of different colonies merge, affinity fades while they keep apart
+    let mut overlapping = HashSet::new();
+    for ((a, b), count) in contacts {
+        let (a_base, b_base) = match (bases.get(a), bases.get(b)) {
+            (Ok((a_base, _, _)), Ok((b_base, _, _))) => (a_base, b_base),
+            _ => continue,
+        };
+        if count >= config.contact_tiles && !a_base.colony.intersects(b_base.colony) {
+            overlapping.insert((a, b));
+        }
+    }
+    for nests in overlapping.iter() {
+        *affinity.0.entry(*nests).or_default() += config.merge_clock;
+    }
+    affinity.0.retain(|nests, overlap| {
+        if !overlapping.contains(nests) {
+            *overlap -= config.merge_clock;
+        }
+        *overlap > 0.0 && bases.get(nests.0).is_ok() && bases.get(nests.1).is_ok()
+    });
+
+    let (a, b) = match affinity
+        .0
+        .iter()
+        .find(|(_, overlap)| **overlap >= config.merge_time)
+    {
+        Some((nests, _)) => *nests,
+        None => return,
+    };
+
+    // the nest with more in store takes in the other
+    let food = |nest: Entity| bases.get(nest).map(|(base, _, _)| base.food.total());
+    let (keep, gone) = if food(a).unwrap_or(0) >= food(b).unwrap_or(0) {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    let (gone_food, gone_colony, gone_pos) = match bases.get(gone) {
+        Ok((base, _, pos)) => (base.food, base.colony, pos.0),
+        Err(_) => return,
+    };
+    map[gone_pos] &= !gone_colony;
+    commands.entity(gone).despawn_recursive();
+
+    let (mut base, mut flag, pos) = match bases.get_mut(keep) {
+        Ok(nest) => nest,
+        Err(_) => return,
+    };
+    // moving food from base to base keeps the ledger balanced
+    for (kept, merged) in base.food.0.iter_mut().zip(gone_food.0.iter()) {
+        *kept += merged;
+    }
+    base.colony |= gone_colony;
+    *flag = base.colony;
+    map[pos.0] |= base.colony;
+
+    let home = pos.0 + Vec2::new(0.5, 0.5);
+    for (mut colonist, mut colony, _, _, scout) in drones.iter_mut() {
+        if colonist.nest != keep && colonist.nest != gone {
+            continue;
+        }
+        *colony = base.colony;
+        colonist.nest = keep;
+        match scout {
+            // scouts keep flying to their site, and come back to the merged nest after
+            Some(mut scout) => scout.home = home,
+            None => colonist.home = home,
+        }
+    }
+
+    // the merged nest is a new colony, its old affinities no longer apply
+    affinity
+        .0
+        .retain(|nests, _| ![keep, gone].contains(&nests.0) && ![keep, gone].contains(&nests.1));
+    info!(
+        "colonies {:?} and {:?} merged into {:?} at {}",
+        base.colony & !gone_colony,
+        gone_colony,
+        base.colony,
+        pos.0
+    );
+}
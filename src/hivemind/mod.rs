@@ -1,12 +1,27 @@
+pub mod alarm;
+pub mod behavior;
+pub mod caste;
 pub mod colony;
+pub mod disease;
+pub mod explore;
 pub mod field;
 pub mod field_systems;
+pub mod merge;
+pub mod neighbors;
+pub mod queen;
+pub mod resource;
+pub mod swarm;
+pub mod territory;
 
-pub use colony::Drone;
+pub use colony::{Base, Drone, Payload};
+pub use resource::{Forage, Ledger, Resource, Stores};
 
 /// Hivemind AI implementation. Ported (with modifications) from johnBuffer's incredible [Ant Simulator project](https://github.com/johnBuffer/AntSimulator/blob/master/include/simulation/world/world_grid.hpp)
 use bevy::prelude::*;
 use bevy::tasks::prelude::*;
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+use std::collections::HashSet;
 
 use crate::world;
 
@@ -15,36 +30,207 @@ pub type VectorField = field::Vector<{ crate::WORLD_SIZE }, { crate::WORLD_SIZE
 /// Convenience types for world sized scalar fields
 pub type ScalarField = field::Scalar<{ crate::WORLD_SIZE }, { crate::WORLD_SIZE }>;
 
-pub struct GatherEvent(pub Vec2);
-pub struct DepositEvent(pub Vec2);
+/// a drone taking a unit of food off the tile it's on
+pub struct GatherEvent {
+    pub drone: Entity,
+    pub colony: world::Flag,
+    pub pos: Vec2,
+}
+
+/// a drone unloading its payload for the colony it belongs to
+pub struct DepositEvent {
+    pub drone: Entity,
+    pub colony: world::Flag,
+    /// the nest whose base gets the payload
+    pub nest: Entity,
+}
 
-/// udpate the world with gather info
-pub fn gather(mut map: ResMut<world::WorldMap>, mut events: EventReader<GatherEvent> ) {
+/// move food off tiles and into the payloads of the drones gathering it
+pub fn gather(
+    mut map: ResMut<world::WorldMap>,
+    forage: Res<Forage>,
+    config: Res<colony::Config>,
+    mut ledger: ResMut<Ledger>,
+    mut drones: Query<&mut Payload>,
+    mut events: EventReader<GatherEvent>,
+) {
+    let mut rng = SmallRng::from_entropy();
     for event in events.iter() {
-        let resource = map[event.0].get_resource_quantity();
-        if resource > 0 {
-            map[event.0].set_resource_quantity(resource - 1);
-        } else {
-            map[event.0] &= !world::Flag::HIVE_FOOD;
+        let mut payload = match drones.get_mut(event.drone) {
+            Ok(payload) => payload,
+            Err(_) => continue,
+        };
+        if payload.amount >= config.capacity {
+            continue;
+        }
+
+        let cell = map[event.pos];
+        let quantity = cell.get_resource_quantity();
+        if quantity == 0 {
+            continue;
+        }
+        let resource = match forage.pick(cell, event.colony, payload.resource, &mut rng) {
+            Some(resource) => resource,
+            None => continue,
+        };
+
+        map[event.pos].set_resource_quantity(quantity - 1);
+        if quantity == 1 && forage.depletes(cell) {
+            map[event.pos] &= !forage.food();
         }
+        payload.resource = Some(resource);
+        payload.amount += 1;
+        ledger.gathered += 1;
     }
 }
 
-/// update the world with deposit info
-pub fn deposit(mut map: ResMut<world::WorldMap>, mut events: EventReader<DepositEvent> ) {
+/// move payloads into the base of the colony the depositing drone belongs to
+pub fn deposit(
+    mut ledger: ResMut<Ledger>,
+    mut bases: Query<&mut Base>,
+    mut drones: Query<&mut Payload>,
+    mut events: EventReader<DepositEvent>,
+) {
     for event in events.iter() {
-        let resource = map[event.0].get_resource_quantity();
-        if resource < world::Flag::MAX_RESOURCE_COUNT {
-            map[event.0].set_resource_quantity(resource + 1);
+        let mut payload = match drones.get_mut(event.drone) {
+            Ok(payload) => payload,
+            Err(_) => continue,
+        };
+        let resource = match payload.resource {
+            Some(resource) => resource,
+            None => continue,
+        };
+        // a drone with no base left to go to holds on to what it carries
+        if let Ok(mut base) = bases.get_mut(event.nest) {
+            if base.colony != event.colony {
+                continue;
+            }
+            base.food[resource] += payload.amount;
+            ledger.deposited += payload.amount;
+            *payload = Payload::default();
         }
     }
 }
 
+/// Drones killed this frame. Whatever kills a drone books it here, so a drone two things killed
+/// at once only dies once
+#[derive(Default)]
+pub struct Deaths(HashSet<Entity>);
+
+impl Deaths {
+    /// mark a drone dead, false if something already killed it this frame
+    pub fn kill(&mut self, drone: Entity) -> bool {
+        self.0.insert(drone)
+    }
+
+    pub fn dead(&self, drone: Entity) -> bool {
+        self.0.contains(&drone)
+    }
+}
+
+/// despawn the drones killed this frame, booking what they carried as dropped and raising the
+/// alarm where they fell
+pub fn bury(
+    mut commands: Commands,
+    alarm: Res<alarm::Config>,
+    mut deaths: ResMut<Deaths>,
+    mut ledger: ResMut<Ledger>,
+    mut alarms: EventWriter<alarm::AlarmEvent>,
+    drones: Query<(&Payload, &world::Position)>,
+) {
+    for drone in deaths.0.drain() {
+        if let Ok((payload, pos)) = drones.get(drone) {
+            ledger.dropped += payload.amount;
+            alarms.send(alarm::AlarmEvent {
+                pos: pos.0,
+                strength: alarm.death,
+            });
+            commands.entity(drone).despawn();
+        }
+    }
+}
+
+/// check that food moving between tiles, drones and bases is all accounted for
+pub fn audit(
+    map: Res<world::WorldMap>,
+    forage: Res<Forage>,
+    ledger: Res<Ledger>,
+    bases: Query<&Base>,
+    payloads: Query<&Payload>,
+) {
+    let mut tiles = 0;
+    for y in 0..map.h() {
+        for x in 0..map.w() {
+            tiles += forage.on_tile(map[y][x]);
+        }
+    }
+    let carried = payloads.iter().map(|payload| payload.amount).sum();
+    let stored = bases.iter().map(|base| base.food.total()).sum();
+    if !ledger.balanced(tiles, carried, stored) {
+        error!(
+            "food not conserved: {} on tiles, {} carried and {} stored, expected {}, {} and {} \
+             from {:?}",
+            tiles,
+            carried,
+            stored,
+            ledger.tiles(),
+            ledger.carried(),
+            ledger.stored(),
+            *ledger
+        );
+    }
+}
+
 pub struct Plugin;
 
 impl bevy::prelude::Plugin for Plugin {
     fn build(&self, app: &mut App) {
+        let merge_config = merge::Config::default();
+        let territory_config = territory::Config::default();
+        let queen_config = queen::Config::default();
+        let disease_config = disease::Config::default();
         app.insert_resource(colony::Config::default())
+            .insert_resource(field_systems::Config::default())
+            .insert_resource(alarm::Config::default())
+            .add_event::<alarm::AlarmEvent>()
+            .insert_resource(behavior::Behavior::load())
+            .init_resource::<behavior::Watch>()
+            .insert_resource(caste::Config::default())
+            .insert_resource(disease::EpidemicClock(Timer::from_seconds(
+                disease_config.clock,
+                true,
+            )))
+            .insert_resource(disease_config)
+            .init_resource::<disease::Blight>()
+            .init_resource::<disease::Epidemic>()
+            .insert_resource(explore::Config::default())
+            .init_resource::<explore::Discoveries>()
+            .insert_resource(neighbors::Config::default())
+            .init_resource::<neighbors::SpatialHash>()
+            .init_resource::<caste::Threats>()
+            .insert_resource(merge::MergeClock(Timer::from_seconds(
+                merge_config.merge_clock,
+                true,
+            )))
+            .insert_resource(merge_config)
+            .init_resource::<merge::Affinity>()
+            .insert_resource(territory::RivalryClock(Timer::from_seconds(
+                territory_config.clock,
+                true,
+            )))
+            .insert_resource(territory_config)
+            .insert_resource(queen::QueenClock(Timer::from_seconds(
+                queen_config.clock,
+                true,
+            )))
+            .insert_resource(queen_config)
+            .add_event::<queen::QueenEvent>()
+            .init_resource::<territory::Territories>()
+            .init_resource::<territory::Rivalry>()
+            .insert_resource(Forage::load())
+            .insert_resource(swarm::Config::default())
+            .init_resource::<Ledger>()
+            .init_resource::<Deaths>()
             .add_event::<GatherEvent>()
             .add_event::<DepositEvent>()
             .add_startup_system_set_to_stage(
@@ -66,6 +252,8 @@ impl bevy::prelude::Plugin for Plugin {
                     .with_system(update_all_scalar_fields)
                     .with_system(update_all_vector_fields)
                     .with_system(gather)
+                    .with_system(behavior::reload)
+                    .with_system(neighbors::rebuild)
                     .with_system(deposit),
             )
             .add_system_set(
@@ -74,7 +262,18 @@ impl bevy::prelude::Plugin for Plugin {
                     .after(world::Order::WorldUpdate)
                     .with_system(colony::update_drones)
                     .with_system(colony::update_drone_sprites)
-                    .with_system(colony::update_colony),
+                    .with_system(colony::update_colony)
+                    .with_system(swarm::swarm)
+                    .with_system(swarm::scout)
+                    .with_system(swarm::found)
+                    .with_system(merge::merge)
+                    .with_system(territory::encounter.label(territory::RivalryOrder::Encounter))
+                    .with_system(territory::survey.after(territory::RivalryOrder::Encounter))
+                    .with_system(queen::crown)
+                    .with_system(queen::rule)
+                    .with_system(disease::spread)
+                    .with_system(disease::progress)
+                    .with_system(disease::respond),
             )
             .add_system_set(
                 SystemSet::new()
@@ -83,9 +282,19 @@ impl bevy::prelude::Plugin for Plugin {
                     .with_system(field_systems::update_attractor)
                     .with_system(field_systems::update_repellent)
                     .with_system(field_systems::update_density)
+                    .with_system(field_systems::update_discovery)
+                    .with_system(alarm::raise)
                     .with_system(field_systems::update_world)
-                    .with_system(colony::signal_drones),
-            );
+                    .with_system(colony::signal_drones)
+                    .with_system(territory::mark)
+                    .with_system(explore::record)
+                    .with_system(queen::log_decisions),
+            )
+            // after every killer has had its say this frame
+            .add_system_to_stage(CoreStage::PostUpdate, bury)
+            // once the despawns of dead drones and nests have been applied, so nothing already
+            // booked as lost is still counted
+            .add_system_to_stage(CoreStage::Last, audit);
     }
 }
 
@@ -0,0 +1,215 @@
+/// Drones sensing the drones right around them. A spatial hash of every drone is rebuilt each tick,
+/// read only while drones update so lookups work from inside parallel queries
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use super::colony::{Drone, Memory};
+use crate::world::{self, Flag};
+
+#[derive(Debug)]
+pub struct Config {
+    /// how far a drone senses others, in tiles. Also the size of hash cells
+    pub radius: f32,
+    /// chance a drone without a patch in mind learns one from a nestmate it runs into
+    pub share_chance: f64,
+    /// how close to home counts as the nest entrance
+    pub entrance: f32,
+    /// drones around an entrance before it counts as congested
+    pub crowd: usize,
+}
+
+impl Config {
+    pub fn default() -> Self {
+        Self {
+            radius: 1.0,
+            share_chance: 0.1,
+            entrance: 2.0,
+            crowd: 4,
+        }
+    }
+}
+
+/// What a drone can tell about another right next to it
+#[derive(Debug, Clone, Copy)]
+pub struct Neighbor {
+    pub drone: Entity,
+    pub pos: Vec2,
+    pub direction: Vec2,
+    pub colony: Flag,
+    /// the patch it remembers gathering from
+    pub patch: Option<Vec2>,
+}
+
+/// Drones bucketed by the cell they're in
+#[derive(Default)]
+pub struct SpatialHash {
+    cell: f32,
+    cells: HashMap<IVec2, Vec<Neighbor>>,
+}
+
+impl SpatialHash {
+    fn key(&self, pos: Vec2) -> IVec2 {
+        (pos / self.cell).floor().as_ivec2()
+    }
+
+    fn insert(&mut self, neighbor: Neighbor) {
+        let key = self.key(neighbor.pos);
+        self.cells.entry(key).or_default().push(neighbor);
+    }
+
+    /// Every other drone within `radius` of `pos`, `radius` shouldn't be larger than a cell
+    pub fn near(&self, drone: Entity, pos: Vec2, radius: f32) -> impl Iterator<Item = &Neighbor> {
+        let key = self.key(pos);
+        (-1..=1)
+            .flat_map(move |y| (-1..=1).map(move |x| key + IVec2::new(x, y)))
+            .filter_map(move |key| self.cells.get(&key))
+            .flatten()
+            .filter(move |n| n.drone != drone && n.pos.distance(pos) <= radius)
+    }
+}
+
+/// Rebuild the hash from where every drone is this tick
+pub fn rebuild(
+    config: Res<Config>,
+    mut hash: ResMut<SpatialHash>,
+    drones: Query<(Entity, &Drone, &world::Position, &Flag, &Memory)>,
+) {
+    hash.cell = config.radius;
+    hash.cells.clear();
+    for (entity, drone, pos, colony, memory) in drones.iter() {
+        hash.insert(Neighbor {
+            drone: entity,
+            pos: pos.0,
+            direction: drone.direction,
+            colony: *colony,
+            patch: memory.patch,
+        });
+    }
+}
+
+/// What a drone makes of the drones around it
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Sensed {
+    /// away from everyone too close, more so the closer they are
+    pub separation: Vec2,
+    /// the average heading of nestmates
+    pub alignment: Vec2,
+    /// away from the crowd at a congested nest entrance, zero elsewhere
+    pub congestion: Vec2,
+    /// a patch some nestmate remembers
+    pub patch: Option<Vec2>,
+}
+
+impl Sensed {
+    pub fn new(
+        hash: &SpatialHash,
+        config: &Config,
+        drone: Entity,
+        pos: Vec2,
+        colony: Flag,
+        home: Vec2,
+    ) -> Self {
+        let mut sensed = Self::default();
+        let mut crowd = 0;
+        let mut nestmates = 0;
+        for n in hash.near(drone, pos, config.radius) {
+            crowd += 1;
+            let away = pos - n.pos;
+            sensed.separation += away.normalize_or_zero() / away.length().max(0.1);
+            if n.colony == colony {
+                nestmates += 1;
+                sensed.alignment += n.direction;
+                sensed.patch = sensed.patch.or(n.patch);
+            }
+        }
+        if nestmates > 0 {
+            sensed.alignment /= nestmates as f32;
+        }
+        if crowd >= config.crowd && pos.distance(home) <= config.entrance {
+            sensed.congestion = sensed.separation * crowd as f32 / config.crowd as f32;
+        }
+        sensed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(neighbors: &[(u32, Vec2, Flag)]) -> SpatialHash {
+        let mut hash = SpatialHash {
+            cell: Config::default().radius,
+            cells: HashMap::new(),
+        };
+        for (id, pos, colony) in neighbors {
+            hash.insert(Neighbor {
+                drone: Entity::from_raw(*id),
+                pos: *pos,
+                direction: Vec2::X,
+                colony: *colony,
+                patch: None,
+            });
+        }
+        hash
+    }
+
+    #[test]
+    fn near_reaches_across_cells_but_not_past_radius() {
+        let pos = Vec2::new(1.9, 1.5);
+        let hash = hash(&[
+            (0, pos, Flag::COLONY_Y),
+            // next cell over, within reach
+            (1, Vec2::new(2.8, 1.5), Flag::COLONY_Y),
+            // diagonal cell, within reach
+            (2, Vec2::new(2.1, 0.9), Flag::COLONY_Y),
+            // scanned cell, out of reach
+            (3, Vec2::new(2.7, 2.5), Flag::COLONY_Y),
+            // two cells over
+            (4, Vec2::new(3.1, 1.5), Flag::COLONY_Y),
+        ]);
+        let mut near: Vec<u32> = hash
+            .near(Entity::from_raw(0), pos, 1.0)
+            .map(|n| n.drone.id())
+            .collect();
+        near.sort_unstable();
+        assert_eq!(near, vec![1, 2]);
+    }
+
+    #[test]
+    fn near_skips_the_drone_itself() {
+        let pos = Vec2::new(5.5, 5.5);
+        let hash = hash(&[(0, pos, Flag::COLONY_Y), (1, pos, Flag::COLONY_M)]);
+        let near: Vec<Entity> = hash.near(Entity::from_raw(0), pos, 1.0).map(|n| n.drone).collect();
+        assert_eq!(near, vec![Entity::from_raw(1)]);
+    }
+
+    #[test]
+    fn sensed_aligns_with_nestmates_and_crowds_only_at_the_entrance() {
+        let config = Config::default();
+        let pos = Vec2::new(5.5, 5.5);
+        let mut hash = hash(&[
+            (1, pos + Vec2::new(0.5, 0.0), Flag::COLONY_Y),
+            (2, pos + Vec2::new(0.0, 0.5), Flag::COLONY_Y),
+            (3, pos + Vec2::new(-0.8, 0.0), Flag::COLONY_M),
+        ]);
+        hash.insert(Neighbor {
+            drone: Entity::from_raw(4),
+            pos: pos + Vec2::new(0.0, -0.8),
+            direction: -Vec2::X,
+            colony: Flag::COLONY_M,
+            patch: Some(Vec2::ZERO),
+        });
+        let drone = Entity::from_raw(0);
+
+        let far = Sensed::new(&hash, &config, drone, pos, Flag::COLONY_Y, Vec2::ZERO);
+        // rivals neither steer the heading nor share their patch
+        assert_eq!(far.alignment, Vec2::X);
+        assert_eq!(far.patch, None);
+        // pushed away from the close nestmates more than toward the rivals further off
+        assert!(far.separation.x < 0.0 && far.separation.y < 0.0);
+        assert_eq!(far.congestion, Vec2::ZERO);
+
+        let home = Sensed::new(&hash, &config, drone, pos, Flag::COLONY_Y, pos);
+        assert_eq!(home.congestion, home.separation);
+    }
+}
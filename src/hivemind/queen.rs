@@ -0,0 +1,220 @@
+/// The colony brain. Every so often each nest's queen reads its stores, population and how much of
+/// the land around it the colony has marked, then picks a mode: expand into new ground, exploit
+/// the patches already known, or recall drones home while starving. Each mode steers how many
+/// drones explore versus forage by tuning their explore threshold
+use bevy::prelude::*;
+
+use super::{
+    colony::{Base, Colonist, DroneState},
+    territory::{self, Territory},
+    Drone, Forage, Payload, ScalarField,
+};
+use crate::world::{self, Flag};
+
+#[derive(Debug)]
+pub struct Config {
+    /// the real-time period between queen decisions
+    pub clock: f32,
+    /// drones' worth of stores below which a colony recalls its drones
+    pub starving: u32,
+    /// tiles around the nest whose marking counts towards coverage
+    pub radius: i32,
+    /// share of the land around the nest the colony has to mark before it stops expanding
+    pub coverage: f32,
+    /// share of drones each mode wants out exploring
+    pub expand_share: f32,
+    pub exploit_share: f32,
+    /// how much the explore threshold moves per decision, and how far it can go
+    pub step: f32,
+    pub min_bias: f32,
+    pub max_bias: f32,
+}
+
+impl Config {
+    pub fn default() -> Self {
+        Self {
+            clock: 5.0,
+            starving: 3,
+            radius: 12,
+            coverage: 0.3,
+            expand_share: 0.4,
+            exploit_share: 0.15,
+            step: 1.25,
+            min_bias: 0.25,
+            max_bias: 4.0,
+        }
+    }
+}
+
+/// Time until queens next decide
+pub struct QueenClock(pub Timer);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Expand,
+    Exploit,
+    Recall,
+}
+
+/// A nest's strategy
+#[derive(Component, Debug)]
+pub struct Queen {
+    pub mode: Mode,
+    /// scales the explore threshold of the nest's drones, higher sends more out exploring
+    pub explore_bias: f32,
+}
+
+impl Default for Queen {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Exploit,
+            explore_bias: 1.0,
+        }
+    }
+}
+
+/// A queen's decision and what it was based on
+#[derive(Debug)]
+pub struct QueenEvent {
+    pub nest: Entity,
+    pub colony: Flag,
+    pub mode: Mode,
+    pub explore_bias: f32,
+    /// drones the stores could still raise
+    pub affordable: u32,
+    pub drones: usize,
+    pub exploring: usize,
+    pub coverage: f32,
+}
+
+/// Share of the tiles within `radius` of `nest` the colony has marked as its own
+fn coverage(field: &ScalarField, nest: Vec2, radius: i32, threshold: f32) -> f32 {
+    let mut marked = 0;
+    let mut total = 0;
+    for y in -radius..=radius {
+        for x in -radius..=radius {
+            let tile = nest + Vec2::new(x as f32, y as f32);
+            if tile.x < 0.0
+                || tile.y < 0.0
+                || tile.x >= crate::WORLD_SIZE as f32
+                || tile.y >= crate::WORLD_SIZE as f32
+            {
+                continue;
+            }
+            total += 1;
+            if field[tile] >= threshold {
+                marked += 1;
+            }
+        }
+    }
+    marked as f32 / total.max(1) as f32
+}
+
+/// Every nest gets a queen
+pub fn crown(mut commands: Commands, nests: Query<Entity, (With<Base>, Without<Queen>)>) {
+    for nest in nests.iter() {
+        commands.entity(nest).insert(Queen::default());
+    }
+}
+
+/// Queens read their colony and pick a mode, tuning how adventurous its drones are
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn rule(
+    time_step: Res<world::TimeStep>,
+    config: Res<Config>,
+    territory_config: Res<territory::Config>,
+    forage: Res<Forage>,
+    mut clock: ResMut<QueenClock>,
+    mut events: EventWriter<QueenEvent>,
+    mut nests: Query<(Entity, &Base, &world::Position, &mut Queen)>,
+    fields: Query<(&ScalarField, &Territory)>,
+    mut drones: Query<(&Colonist, &mut DroneState, &Payload), With<Drone>>,
+) {
+    if !clock.0.tick(time_step.into_inner().into()).just_finished() {
+        return;
+    }
+
+    for (nest, base, pos, mut queen) in nests.iter_mut() {
+        let needs = forage.needs(base.colony);
+        let affordable = needs
+            .kinds()
+            .map(|kind| base.food[kind] / needs[kind])
+            .min()
+            .unwrap_or(0);
+        let (mut population, mut exploring) = (0, 0);
+        for (colonist, state, _) in drones.iter() {
+            if colonist.nest == nest {
+                population += 1;
+                if *state == DroneState::Exploring {
+                    exploring += 1;
+                }
+            }
+        }
+        let coverage = fields
+            .iter()
+            .find(|(_, territory)| territory.0 == base.colony)
+            .map_or(0.0, |(field, _)| {
+                coverage(field, pos.0, config.radius, territory_config.threshold)
+            });
+
+        let mode = if affordable < config.starving {
+            Mode::Recall
+        } else if coverage < config.coverage {
+            Mode::Expand
+        } else {
+            Mode::Exploit
+        };
+        let share = match mode {
+            Mode::Expand => config.expand_share,
+            Mode::Exploit => config.exploit_share,
+            Mode::Recall => 0.0,
+        };
+        // nudge the explore threshold until the share of drones out exploring matches the mode
+        if (exploring as f32) < share * population as f32 {
+            queen.explore_bias *= config.step;
+        } else {
+            queen.explore_bias /= config.step;
+        }
+        queen.explore_bias = queen.explore_bias.clamp(config.min_bias, config.max_bias);
+
+        // a starving colony calls its empty-handed explorers home to work the known patches
+        if mode == Mode::Recall {
+            for (colonist, mut state, payload) in drones.iter_mut() {
+                if colonist.nest == nest && *state == DroneState::Exploring && payload.amount == 0
+                {
+                    *state = DroneState::ToHomeNoFood;
+                }
+            }
+        }
+
+        if mode != queen.mode {
+            queen.mode = mode;
+            events.send(QueenEvent {
+                nest,
+                colony: base.colony,
+                mode,
+                explore_bias: queen.explore_bias,
+                affordable,
+                drones: population,
+                exploring,
+                coverage,
+            });
+        }
+    }
+}
+
+pub fn log_decisions(mut events: EventReader<QueenEvent>) {
+    for event in events.iter() {
+        info!(
+            "queen of colony {:?} chose to {:?}: {} drones affordable, {}/{} exploring, {:.0}% \
+             covered, explore bias {:.2}",
+            event.colony,
+            event.mode,
+            event.affordable,
+            event.exploring,
+            event.drones,
+            event.coverage * 100.0,
+            event.explore_bias
+        );
+    }
+}
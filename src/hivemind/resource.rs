@@ -0,0 +1,395 @@
+/// Resource kinds drones forage, which tiles yield them, and what each colony needs
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::ops::{Index, IndexMut};
+
+use crate::world::{DeRon, Flag};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resource {
+    Nectar,
+    Pollen,
+    Sap,
+}
+
+impl Resource {
+    pub const COUNT: usize = 3;
+    pub const ALL: [Resource; Resource::COUNT] =
+        [Resource::Nectar, Resource::Pollen, Resource::Sap];
+
+    pub fn parse(name: &str) -> Self {
+        match name {
+            "nectar" => Resource::Nectar,
+            "pollen" => Resource::Pollen,
+            "sap" => Resource::Sap,
+            name => panic!("ERROR: unknown resource kind '{}'", name),
+        }
+    }
+}
+
+/// An amount of every resource kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stores(pub [u32; Resource::COUNT]);
+
+impl Index<Resource> for Stores {
+    type Output = u32;
+
+    fn index(&self, resource: Resource) -> &u32 {
+        &self.0[resource as usize]
+    }
+}
+
+impl IndexMut<Resource> for Stores {
+    fn index_mut(&mut self, resource: Resource) -> &mut u32 {
+        &mut self.0[resource as usize]
+    }
+}
+
+impl Stores {
+    fn parse(amounts: &[(String, u32)]) -> Self {
+        let mut stores = Self::default();
+        for (name, amount) in amounts {
+            stores[Resource::parse(name)] += amount;
+        }
+        stores
+    }
+
+    pub fn total(&self) -> u32 {
+        self.0.iter().sum()
+    }
+
+    /// Resource kinds with anything in store
+    pub fn kinds(&self) -> impl Iterator<Item = Resource> + '_ {
+        Resource::ALL.iter().copied().filter(move |r| self[*r] > 0)
+    }
+}
+
+/// Forage file form of a tile kind's yields
+///
+/// `kind` is a tile kind such as "flower" or "stump", `yields` pairs resource kinds with the
+/// weight they're picked with
+#[derive(Debug, Clone, DeRon)]
+pub struct TileYields {
+    pub kind: String,
+    pub yields: Vec<(String, u32)>,
+    /// whether gathering the tile dry removes it
+    pub depletes: bool,
+}
+
+/// Forage file form of a colony's needs
+///
+/// `colony` is one of "y", "m" or "c"
+#[derive(Debug, Clone, DeRon)]
+pub struct Diet {
+    pub colony: String,
+    /// spent on every drone raised
+    pub needs: Vec<(String, u32)>,
+    pub starting: Vec<(String, u32)>,
+}
+
+/// Forage file contents
+#[derive(DeRon)]
+pub struct ForageFile {
+    pub tiles: Vec<TileYields>,
+    pub diets: Vec<Diet>,
+}
+
+fn parse_tile(kind: &str) -> Flag {
+    match kind {
+        "flower" => Flag::FLOWER,
+        "tree" => Flag::TREE,
+        "stump" => Flag::STUMP,
+        kind => panic!("ERROR: unknown forage tile kind '{}'", kind),
+    }
+}
+
+fn parse_colony(colony: &str) -> Flag {
+    match colony {
+        "y" => Flag::COLONY_Y,
+        "m" => Flag::COLONY_M,
+        "c" => Flag::COLONY_C,
+        colony => panic!("ERROR: unknown colony '{}'", colony),
+    }
+}
+
+struct Tile {
+    kind: Flag,
+    yields: Vec<(Resource, u32)>,
+    depletes: bool,
+}
+
+struct Appetite {
+    colony: Flag,
+    needs: Stores,
+    starting: Stores,
+}
+
+/// What every tile kind yields and what every colony eats
+pub struct Forage {
+    tiles: Vec<Tile>,
+    diets: Vec<Appetite>,
+}
+
+impl Forage {
+    // TODO: remove assets hardcoded path
+    pub fn load() -> Self {
+        let bytes = include_str!("../../assets/forage.ron");
+        let file: ForageFile =
+            DeRon::deserialize_ron(bytes).expect("ERROR: failed to load forage config");
+
+        let tiles = file
+            .tiles
+            .iter()
+            .map(|t| Tile {
+                kind: parse_tile(&t.kind),
+                yields: t
+                    .yields
+                    .iter()
+                    .map(|(name, weight)| (Resource::parse(name), *weight))
+                    .collect(),
+                depletes: t.depletes,
+            })
+            .collect();
+        let diets = file
+            .diets
+            .iter()
+            .map(|d| Appetite {
+                colony: parse_colony(&d.colony),
+                needs: Stores::parse(&d.needs),
+                starting: Stores::parse(&d.starting),
+            })
+            .collect();
+
+        let forage = Self { tiles, diets };
+        let unsourced = forage.unsourced();
+        if !unsourced.is_empty() {
+            panic!("ERROR: forage config diets need {:?}, which no tile yields", unsourced);
+        }
+        forage
+    }
+
+    /// Resources some diet needs that no tile kind yields, colonies needing them would starve
+    fn unsourced(&self) -> Vec<Resource> {
+        Resource::ALL
+            .iter()
+            .copied()
+            .filter(|r| self.diets.iter().any(|d| d.needs[*r] > 0))
+            .filter(|r| !self.tiles.iter().any(|t| t.yields.iter().any(|(y, _)| y == r)))
+            .collect()
+    }
+
+    fn tile(&self, cell: Flag) -> Option<&Tile> {
+        self.tiles.iter().find(|t| cell.intersects(t.kind))
+    }
+
+    fn diet(&self, colony: Flag) -> Option<&Appetite> {
+        self.diets.iter().find(|d| d.colony == colony)
+    }
+
+    /// Tile kinds that yield anything at all
+    pub fn food(&self) -> Flag {
+        self.tiles
+            .iter()
+            .fold(Flag::EMPTY, |food, tile| food | tile.kind)
+    }
+
+    /// What a colony spends raising a drone. Merged colonies spend the average of their members,
+    /// rounded up
+    pub fn needs(&self, colony: Flag) -> Stores {
+        if let Some(diet) = self.diet(colony) {
+            return diet.needs;
+        }
+        let members: Vec<&Appetite> = self
+            .diets
+            .iter()
+            .filter(|d| colony.contains(d.colony))
+            .collect();
+        let mut needs = Stores::default();
+        if members.is_empty() {
+            return needs;
+        }
+        for resource in Resource::ALL {
+            let total: u32 = members.iter().map(|d| d.needs[resource]).sum();
+            needs[resource] = (total + members.len() as u32 - 1) / members.len() as u32;
+        }
+        needs
+    }
+
+    /// How much a colony wants a resource, from 0 for what it doesn't eat to 1 for what it needs
+    /// most of
+    pub fn quality(&self, colony: Flag, resource: Resource) -> f32 {
+        let needs = self.needs(colony);
+        let most = Resource::ALL.iter().map(|r| needs[*r]).max().unwrap_or(0);
+        if most == 0 {
+            return 0.0;
+        }
+        needs[resource] as f32 / most as f32
+    }
+
+    /// Food on a tile, nothing if the tile yields nothing
+    pub fn on_tile(&self, cell: Flag) -> u32 {
+        if cell.intersects(self.food()) {
+            cell.get_resource_quantity()
+        } else {
+            0
+        }
+    }
+
+    pub fn starting(&self, colony: Flag) -> Stores {
+        self.diet(colony).map(|d| d.starting).unwrap_or_default()
+    }
+
+    pub fn depletes(&self, cell: Flag) -> bool {
+        self.tile(cell).map(|t| t.depletes).unwrap_or(true)
+    }
+
+    /// Whether a colony's drone can gather anything from this cell right now. Drones only ever
+    /// carry one resource kind at a time, so a partly loaded drone only takes more of the same
+    pub fn edible(&self, cell: Flag, colony: Flag, carrying: Option<Resource>) -> bool {
+        let needs = self.needs(colony);
+        match self.tile(cell) {
+            Some(tile) => {
+                cell.get_resource_quantity() > 0
+                    && tile
+                        .yields
+                        .iter()
+                        .any(|(r, _)| needs[*r] > 0 && carrying.map_or(true, |c| c == *r))
+            }
+            None => false,
+        }
+    }
+
+    /// Pick the resource a colony's drone takes from this cell, weighted by the cell's yields
+    pub fn pick(
+        &self,
+        cell: Flag,
+        colony: Flag,
+        carrying: Option<Resource>,
+        rng: &mut impl Rng,
+    ) -> Option<Resource> {
+        let needs = self.needs(colony);
+        let tile = self.tile(cell)?;
+        tile.yields
+            .iter()
+            .filter(|(r, _)| needs[*r] > 0 && carrying.map_or(true, |c| c == *r))
+            .collect::<Vec<_>>()
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .ok()
+            .map(|(r, _)| *r)
+    }
+}
+
+/// Running totals of food moving between tiles, drone payloads and bases, used to check none is
+/// conjured or lost along the way
+#[derive(Debug, Default)]
+pub struct Ledger {
+    /// put on tiles by anything but drones, from the starting map to flowers growing
+    pub sprouted: u32,
+    /// taken off tiles by anything but drones, such as lava, multivacs or the player
+    pub withered: u32,
+    /// stocked into bases when their colonies were founded
+    pub founded: u32,
+    /// taken off tiles into payloads
+    pub gathered: u32,
+    /// unloaded from payloads into bases
+    pub deposited: u32,
+    /// spent by bases raising drones
+    pub spent: u32,
+    /// carried by drones that died
+    pub dropped: u32,
+    /// left in the bases of colonies that died
+    pub abandoned: u32,
+}
+
+impl Ledger {
+    /// Book food put on or taken off a tile by anything but drones, given the tile's food before
+    /// and after
+    pub fn retile(&mut self, before: u32, after: u32) {
+        if after > before {
+            self.sprouted += after - before;
+        } else {
+            self.withered += before - after;
+        }
+    }
+
+    /// Food that should currently be on tiles
+    pub fn tiles(&self) -> i64 {
+        self.sprouted as i64 - self.gathered as i64 - self.withered as i64
+    }
+
+    /// Food that should currently be in drone payloads
+    pub fn carried(&self) -> i64 {
+        self.gathered as i64 - self.deposited as i64 - self.dropped as i64
+    }
+
+    /// Food that should currently be in bases
+    pub fn stored(&self) -> i64 {
+        self.founded as i64 + self.deposited as i64 - self.spent as i64 - self.abandoned as i64
+    }
+
+    pub fn balanced(&self, tiles: u32, carried: u32, stored: u32) -> bool {
+        tiles as i64 == self.tiles()
+            && carried as i64 == self.carried()
+            && stored as i64 == self.stored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quality_follows_the_diet() {
+        let forage = Forage::load();
+        assert_eq!(forage.quality(Flag::COLONY_Y, Resource::Nectar), 1.0);
+        assert_eq!(forage.quality(Flag::COLONY_Y, Resource::Pollen), 0.5);
+        assert_eq!(forage.quality(Flag::COLONY_Y, Resource::Sap), 0.0);
+    }
+
+    #[test]
+    fn needs_without_a_tile_yielding_them_are_caught() {
+        let mut forage = Forage::load();
+        assert!(forage.unsourced().is_empty());
+        forage.tiles.retain(|t| t.kind != Flag::STUMP);
+        assert_eq!(forage.unsourced(), vec![Resource::Sap]);
+    }
+
+    #[test]
+    fn ledger_balances_transfers() {
+        let mut ledger = Ledger::default();
+        let mut base = Stores::default();
+        let mut payload = 0;
+        let mut tile = Flag::FLOWER;
+
+        // the map starts out with a full flower
+        tile.set_resource_quantity(20);
+        ledger.retile(0, 20);
+        base[Resource::Nectar] += 100;
+        ledger.founded += 100;
+        assert!(ledger.balanced(tile.get_resource_quantity(), payload, base.total()));
+
+        // gather three units, deposit them, then raise a drone
+        payload += 3;
+        tile.set_resource_quantity(17);
+        ledger.gathered += 3;
+        assert!(ledger.balanced(tile.get_resource_quantity(), payload, base.total()));
+        base[Resource::Nectar] += payload;
+        ledger.deposited += payload;
+        payload = 0;
+        assert!(ledger.balanced(tile.get_resource_quantity(), payload, base.total()));
+        base[Resource::Nectar] -= 9;
+        ledger.spent += 9;
+        assert!(ledger.balanced(tile.get_resource_quantity(), payload, base.total()));
+
+        // lava burning the flower down
+        ledger.retile(tile.get_resource_quantity(), 0);
+        tile = Flag::LAVA;
+        assert!(ledger.balanced(tile.get_resource_quantity(), payload, base.total()));
+
+        // food that vanishes without being booked is caught
+        payload += 1;
+        ledger.gathered += 1;
+        payload -= 1;
+        assert!(!ledger.balanced(tile.get_resource_quantity(), payload, base.total()));
+    }
+}
@@ -0,0 +1,284 @@
+/// Swarming. A thriving colony sends scouts out to candidate sites, then splits part of its
+/// population and stores off to found a new nest at the best one
+use bevy::prelude::*;
+use float_ord::FloatOrd;
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+
+use super::{
+    colony::{Base, Colonist, ColonyClock, DroneState, Hibernating},
+    field_systems, Drone, ScalarField,
+};
+use crate::world::{self, Flag, WorldMap};
+
+#[derive(Debug)]
+pub struct Config {
+    /// food a colony needs in store before it swarms
+    pub food: u32,
+    /// drones a colony needs before it swarms
+    pub population: usize,
+    /// seconds from one swarm of a colony to the next
+    pub cooldown: f32,
+    /// candidate sites, one scout flies out to each
+    pub scouts: usize,
+    /// how far from the nest candidate sites can be, in tiles
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// closest a new nest can be to any other
+    pub spacing: f32,
+    /// tiles around a site whose food field counts towards its score
+    pub survey_radius: i32,
+    /// score lost per tile of distance from the parent nest
+    pub distance_cost: f32,
+    /// seconds to wait for scouts before settling on what's been reported
+    pub patience: f32,
+    /// share of the parent's drones and stores that leave with the swarm
+    pub split: f32,
+}
+
+impl Config {
+    pub fn default() -> Self {
+        Self {
+            food: 3000,
+            population: 60,
+            cooldown: 300.0,
+            scouts: 4,
+            min_distance: 12.0,
+            max_distance: 30.0,
+            spacing: 8.0,
+            survey_radius: 3,
+            distance_cost: 2.0,
+            patience: 60.0,
+            split: 0.3,
+        }
+    }
+}
+
+/// A colony that swarmed recently and can't again until the timer runs out
+#[derive(Component)]
+pub struct Swarming(pub Timer);
+
+/// A swarm waiting on its scouts' reports
+#[derive(Component)]
+pub struct Swarm {
+    pub parent: Entity,
+    pub colony: Flag,
+    pub scouts: usize,
+    /// candidate sites with their scores
+    pub reports: Vec<(IVec2, f32)>,
+    pub patience: Timer,
+}
+
+/// A drone flying out to survey a candidate site
+#[derive(Component)]
+pub struct Scout {
+    pub swarm: Entity,
+    pub site: IVec2,
+    /// where the drone goes back to once it has reported
+    pub home: Vec2,
+}
+
+fn too_close(site: IVec2, nests: &[Vec2], spacing: f32) -> bool {
+    nests
+        .iter()
+        .any(|nest| nest.distance(site.as_vec2()) < spacing)
+}
+
+/// Start a swarm from every thriving colony, sending scouts out to candidate sites
+#[allow(clippy::type_complexity)]
+pub fn swarm(
+    mut commands: Commands,
+    time_step: Res<world::TimeStep>,
+    config: Res<Config>,
+    mut swarming: Query<(Entity, &mut Swarming)>,
+    colonies: Query<
+        (Entity, &Base, &world::Position),
+        (Without<Hibernating>, Without<Swarming>),
+    >,
+    mut drones: Query<(Entity, &mut Colonist, &mut DroneState), (With<Drone>, Without<Scout>)>,
+) {
+    let time_step = time_step.into_inner();
+    for (entity, mut cooldown) in swarming.iter_mut() {
+        if cooldown.0.tick(time_step.into()).just_finished() {
+            commands.entity(entity).remove::<Swarming>();
+        }
+    }
+
+    let mut rng = SmallRng::from_entropy();
+    for (nest, base, pos) in colonies.iter() {
+        if base.food.total() < config.food {
+            continue;
+        }
+        let mut colonists: Vec<_> = drones
+            .iter_mut()
+            .filter(|(_, colonist, _)| colonist.nest == nest)
+            .collect();
+        if colonists.len() < config.population {
+            continue;
+        }
+
+        let swarm = commands
+            .spawn()
+            .insert(Swarm {
+                parent: nest,
+                colony: base.colony,
+                scouts: config.scouts,
+                reports: Vec::new(),
+                patience: Timer::from_seconds(config.patience, false),
+            })
+            .id();
+        for (drone, colonist, state) in colonists.iter_mut().take(config.scouts) {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let distance = rng.gen_range(config.min_distance..config.max_distance);
+            let site = (pos.0 + Vec2::new(angle.cos(), angle.sin()) * distance).as_ivec2();
+            commands.entity(*drone).insert(Scout {
+                swarm,
+                site,
+                home: colonist.home,
+            });
+            // scouts fly straight for the site, the same way a drone with nothing to show flies
+            // home
+            colonist.home = site.as_vec2() + Vec2::new(0.5, 0.5);
+            **state = DroneState::ToHomeNoFood;
+        }
+        commands
+            .entity(nest)
+            .insert(Swarming(Timer::from_seconds(config.cooldown, false)));
+        info!("colony {:?} at {} is swarming", base.colony, pos.0);
+    }
+}
+
+/// Scouts that reach their site score it by the food around it and how far it is from home
+pub fn scout(
+    mut commands: Commands,
+    map: Res<WorldMap>,
+    config: Res<Config>,
+    food_field: Query<&ScalarField, With<field_systems::Food>>,
+    mut swarms: Query<&mut Swarm>,
+    mut scouts: Query<(
+        Entity,
+        &Scout,
+        &mut Colonist,
+        &mut DroneState,
+        &world::Position,
+    )>,
+) {
+    let food_f = food_field.single();
+    for (drone, scout, mut colonist, mut state, pos) in scouts.iter_mut() {
+        if pos.0.distance(colonist.home) > 1.0 {
+            continue;
+        }
+
+        if let Ok(mut swarm) = swarms.get_mut(scout.swarm) {
+            let r = config.survey_radius;
+            let mut food = 0.0;
+            for y in -r..=r {
+                for x in -r..=r {
+                    let tile = scout.site + IVec2::new(x, y);
+                    if map.get_ivec2(tile).is_some() {
+                        food += food_f[tile.as_vec2()];
+                    }
+                }
+            }
+            let score = food - scout.home.distance(scout.site.as_vec2()) * config.distance_cost;
+            swarm.reports.push((scout.site, score));
+        }
+
+        colonist.home = scout.home;
+        *state = DroneState::ToFood;
+        commands.entity(drone).remove::<Scout>();
+    }
+}
+
+/// Once every scout reported, or the swarm ran out of patience, found a new nest at the best site
+/// and move part of the parent's drones and stores into it
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn found(
+    mut commands: Commands,
+    mut map: ResMut<WorldMap>,
+    time_step: Res<world::TimeStep>,
+    config: Res<Config>,
+    colony_config: Res<super::colony::Config>,
+    mut swarms: Query<(Entity, &mut Swarm)>,
+    mut bases: Query<(Entity, &mut Base, &world::Position)>,
+    mut drones: Query<(&mut Colonist, &mut DroneState), (With<Drone>, Without<Scout>)>,
+    mut scouts: Query<(Entity, &Scout, &mut Colonist), With<Drone>>,
+) {
+    let time_step = time_step.into_inner();
+    for (entity, mut swarm) in swarms.iter_mut() {
+        let patience = swarm.patience.tick(time_step.into()).finished();
+        if swarm.reports.len() < swarm.scouts && !patience {
+            continue;
+        }
+        commands.entity(entity).despawn();
+
+        // call back scouts that got lost on the way
+        for (drone, scout, mut colonist) in scouts.iter_mut() {
+            if scout.swarm == entity {
+                colonist.home = scout.home;
+                commands.entity(drone).remove::<Scout>();
+            }
+        }
+
+        let nests: Vec<Vec2> = bases.iter().map(|(_, _, pos)| pos.0).collect();
+        let mut reports = swarm.reports.clone();
+        reports.sort_by_key(|(_, score)| FloatOrd(-*score));
+        let site = reports.into_iter().map(|(site, _)| site).find(|site| {
+            matches!(map.get_ivec2(*site), Some(flags) if flags.is_empty())
+                && !too_close(*site, &nests, config.spacing)
+        });
+        let site = match site {
+            Some(site) => site,
+            None => {
+                info!("swarm of colony {:?} found nowhere to settle", swarm.colony);
+                continue;
+            }
+        };
+
+        // split the parent's stores, moving food from base to base keeps the ledger balanced
+        let food = match bases.get_mut(swarm.parent) {
+            Ok((_, mut parent, _)) => {
+                let mut food = parent.food;
+                for amount in food.0.iter_mut() {
+                    *amount = (*amount as f32 * config.split) as u32;
+                }
+                for (kept, left) in parent.food.0.iter_mut().zip(food.0.iter()) {
+                    *kept -= left;
+                }
+                food
+            }
+            // the parent died while the scouts were out
+            Err(_) => continue,
+        };
+
+        let pos = site.as_vec2();
+        map[site] |= swarm.colony;
+        let nest = commands
+            .spawn()
+            .insert(world::Colony)
+            .insert(ColonyClock(Timer::from_seconds(
+                colony_config.spawn_rate,
+                true,
+            )))
+            .insert(Base::new(swarm.colony, pos, food))
+            .insert(world::Position(pos))
+            .insert(swarm.colony)
+            .id();
+
+        let home = pos + Vec2::new(0.5, 0.5);
+        let mut colonists: Vec<_> = drones
+            .iter_mut()
+            .filter(|(colonist, _)| colonist.nest == swarm.parent)
+            .collect();
+        let leaving = (colonists.len() as f32 * config.split) as usize;
+        for (colonist, state) in colonists.iter_mut().take(leaving) {
+            colonist.home = home;
+            colonist.nest = nest;
+            **state = DroneState::ToHomeNoFood;
+        }
+        info!(
+            "swarm of colony {:?} founded a nest at {} with {} drones",
+            swarm.colony, site, leaving
+        );
+    }
+}
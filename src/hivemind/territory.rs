@@ -0,0 +1,328 @@
+/// Competition between colonies. Every colony marks its own pheromone field, whoever marked a
+/// tile the most holds it, and drones of rival colonies that run into each other avoid, fight or
+/// rob one another
+use bevy::prelude::*;
+use float_ord::FloatOrd;
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    alarm::{self, AlarmEvent},
+    caste::{self, Caste, Threat, Threats},
+    colony::Payload,
+    Deaths, Drone, Forage, ScalarField,
+};
+use crate::world::{self, Flag, WorldMap};
+
+#[derive(Debug)]
+pub struct Config {
+    /// pheromone a drone leaves on its colony's field every update
+    pub mark: f32,
+    /// pheromone a colony needs on a tile to claim it
+    pub threshold: f32,
+    /// the real-time period between territory surveys and encounters
+    pub clock: f32,
+    /// relative chances of each encounter outcome
+    pub avoid: f32,
+    pub fight: f32,
+    pub steal: f32,
+    /// how much more likely a drone is to win on its own colony's territory
+    pub home_advantage: f32,
+}
+
+impl Config {
+    pub fn default() -> Self {
+        Self {
+            mark: 0.1,
+            threshold: 1.0,
+            clock: 1.0,
+            avoid: 2.0,
+            fight: 1.0,
+            steal: 1.0,
+            home_advantage: 0.2,
+        }
+    }
+}
+
+/// Time until territory is next surveyed and encounters are next resolved
+pub struct RivalryClock(pub Timer);
+
+/// Encounters tick the rivalry clock, so the survey reading it has to run after them
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum RivalryOrder {
+    Encounter,
+}
+
+/// Pheromone field marked by a single colony's drones
+#[derive(Component)]
+pub struct Territory(pub Flag);
+
+/// Which colony holds every tile, EMPTY for nobody
+#[derive(Default)]
+pub struct Territories {
+    owners: Vec<Flag>,
+    width: usize,
+}
+
+impl Territories {
+    pub fn owner(&self, tile: IVec2) -> Flag {
+        let i = tile.y as usize * self.width + tile.x as usize;
+        self.owners.get(i).copied().unwrap_or(Flag::EMPTY)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Avoid,
+    Fight,
+    Steal,
+}
+
+impl Outcome {
+    /// There is nothing to steal from an empty-handed drone, so both just walk away
+    pub fn resolve(self, loser_carries: bool) -> Self {
+        match self {
+            Outcome::Steal if !loser_carries => Outcome::Avoid,
+            outcome => outcome,
+        }
+    }
+}
+
+/// Competitive record of a single colony
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Record {
+    pub wins: u32,
+    pub losses: u32,
+    /// payloads stolen from rivals
+    pub thefts: u32,
+    /// payloads lost to rivals
+    pub robbed: u32,
+    pub avoided: u32,
+    /// tiles held as of the last survey
+    pub territory: u32,
+    /// food tiles this colony shares a claim on as of the last survey
+    pub contested: u32,
+}
+
+/// Records of every colony that has competed so far
+#[derive(Default)]
+pub struct Rivalry(pub HashMap<Flag, Record>);
+
+/// Drones mark their colony's territory, colonies without a field yet get one
+pub fn mark(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut fields: Query<(&mut ScalarField, &Territory)>,
+    drones: Query<(&Flag, &world::Position), With<Drone>>,
+) {
+    let mut fields: HashMap<Flag, Mut<ScalarField>> = fields
+        .iter_mut()
+        .map(|(field, territory)| (territory.0, field))
+        .collect();
+    let mut unmarked = HashSet::new();
+    for (colony, pos) in drones.iter() {
+        match fields.get_mut(colony) {
+            Some(field) => field[pos.0] += config.mark,
+            None => {
+                unmarked.insert(*colony);
+            }
+        }
+    }
+    for colony in unmarked {
+        commands
+            .spawn()
+            .insert(ScalarField::default())
+            .insert(Territory(colony));
+    }
+}
+
+/// Work out who holds each tile, and which food tiles are contested
+pub fn survey(
+    map: Res<WorldMap>,
+    config: Res<Config>,
+    forage: Res<Forage>,
+    clock: Res<RivalryClock>,
+    fields: Query<(&ScalarField, &Territory)>,
+    mut territories: ResMut<Territories>,
+    mut rivalry: ResMut<Rivalry>,
+) {
+    if !clock.0.just_finished() {
+        return;
+    }
+
+    let food = forage.food();
+    for record in rivalry.0.values_mut() {
+        record.territory = 0;
+        record.contested = 0;
+    }
+    territories.width = map.w();
+    territories.owners = vec![Flag::EMPTY; map.w() * map.h()];
+    for y in 0..map.h() {
+        for x in 0..map.w() {
+            let pos = Vec2::new(x as f32, y as f32);
+            let claims: Vec<(Flag, f32)> = fields
+                .iter()
+                .map(|(field, territory)| (territory.0, field[pos]))
+                .filter(|(_, strength)| *strength >= config.threshold)
+                .collect();
+            let owner = match claims.iter().max_by_key(|(_, strength)| FloatOrd(*strength)) {
+                Some((owner, _)) => *owner,
+                None => continue,
+            };
+            territories.owners[y * map.w() + x] = owner;
+            rivalry.0.entry(owner).or_default().territory += 1;
+            if claims.len() > 1 && map[y][x].intersects(food) {
+                for (colony, _) in claims {
+                    rivalry.0.entry(colony).or_default().contested += 1;
+                }
+            }
+        }
+    }
+
+    for (colony, record) in rivalry.0.iter() {
+        debug!("colony {:?} rivalry: {:?}", colony, record);
+    }
+}
+
+/// Drones of rival colonies sharing a tile avoid each other, fight, or one robs the other. Fights
+/// and thefts raise the alarm for the losing colony's guards
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn encounter(
+    time_step: Res<world::TimeStep>,
+    config: Res<Config>,
+    castes: Res<caste::Config>,
+    alarm: Res<alarm::Config>,
+    mut clock: ResMut<RivalryClock>,
+    territories: Res<Territories>,
+    mut deaths: ResMut<Deaths>,
+    mut rivalry: ResMut<Rivalry>,
+    mut threats: ResMut<Threats>,
+    mut alarms: EventWriter<AlarmEvent>,
+    mut drones: Query<(Entity, &mut Drone, &mut Payload, &Flag, &world::Position, &Caste)>,
+) {
+    if !clock.0.tick(time_step.into_inner().into()).just_finished() {
+        return;
+    }
+    for threat in threats.0.iter_mut() {
+        threat.left -= config.clock;
+    }
+    threats.0.retain(|threat| threat.left > 0.0);
+
+    let mut tiles: HashMap<IVec2, Vec<(Entity, Flag, Caste)>> = HashMap::new();
+    for (entity, _, _, colony, pos, caste) in drones.iter() {
+        if deaths.dead(entity) {
+            continue;
+        }
+        tiles
+            .entry(pos.0.as_ivec2())
+            .or_default()
+            .push((entity, *colony, *caste));
+    }
+
+    let outcomes = [
+        (Outcome::Avoid, config.avoid),
+        (Outcome::Fight, config.fight),
+        (Outcome::Steal, config.steal),
+    ];
+    let mut rng = SmallRng::from_entropy();
+    let mut met = HashSet::new();
+    for (tile, crowd) in tiles {
+        for (i, (a, a_colony, a_caste)) in crowd.iter().enumerate() {
+            for (b, b_colony, b_caste) in &crowd[i + 1..] {
+                if a_colony.intersects(*b_colony) || met.contains(a) || met.contains(b) {
+                    continue;
+                }
+                met.insert(*a);
+                met.insert(*b);
+                alarms.send(AlarmEvent {
+                    pos: tile.as_vec2() + Vec2::new(0.5, 0.5),
+                    strength: alarm.encounter,
+                });
+
+                let owner = territories.owner(tile);
+                let mut odds = 0.5;
+                if owner == *a_colony {
+                    odds += config.home_advantage;
+                } else if owner == *b_colony {
+                    odds -= config.home_advantage;
+                }
+                if *a_caste == Caste::Guard {
+                    odds += castes.guard_advantage;
+                }
+                if *b_caste == Caste::Guard {
+                    odds -= castes.guard_advantage;
+                }
+                let a_wins = rng.gen_bool(odds.clamp(0.0, 1.0) as f64);
+                let ((winner, winner_colony), (loser, loser_colony)) = if a_wins {
+                    ((*a, *a_colony), (*b, *b_colony))
+                } else {
+                    ((*b, *b_colony), (*a, *a_colony))
+                };
+
+                let outcome = outcomes
+                    .choose_weighted(&mut rng, |(_, weight)| *weight)
+                    .map(|(outcome, _)| *outcome)
+                    .unwrap_or(Outcome::Avoid);
+                let loser_carries = drones
+                    .get(loser)
+                    .map_or(false, |(_, _, payload, _, _, _)| payload.amount > 0);
+                let outcome = outcome.resolve(loser_carries);
+                if outcome != Outcome::Avoid {
+                    threats.0.push(Threat {
+                        pos: tile.as_vec2() + Vec2::new(0.5, 0.5),
+                        colony: loser_colony,
+                        left: castes.alarm,
+                    });
+                }
+                let stolen = match drones.get_mut(loser) {
+                    Ok((_, _, mut payload, _, _, _)) if outcome == Outcome::Steal => {
+                        std::mem::take(&mut *payload)
+                    }
+                    _ => Payload::default(),
+                };
+                match outcome {
+                    Outcome::Fight => {
+                        deaths.kill(loser);
+                        rivalry.0.entry(winner_colony).or_default().wins += 1;
+                        rivalry.0.entry(loser_colony).or_default().losses += 1;
+                    }
+                    Outcome::Steal => {
+                        // drones carry one kind at a time, a full-handed thief leaves the rest
+                        if let Ok((_, _, mut payload, _, _, _)) = drones.get_mut(winner) {
+                            if payload.amount == 0 {
+                                *payload = stolen;
+                                rivalry.0.entry(winner_colony).or_default().thefts += 1;
+                                rivalry.0.entry(loser_colony).or_default().robbed += 1;
+                            } else if let Ok((_, _, mut payload, _, _, _)) = drones.get_mut(loser) {
+                                *payload = stolen;
+                            }
+                        }
+                    }
+                    _ => {
+                        for drone in [*a, *b] {
+                            if let Ok((_, mut drone, _, _, _, _)) = drones.get_mut(drone) {
+                                drone.direction *= -1.0;
+                            }
+                        }
+                        rivalry.0.entry(*a_colony).or_default().avoided += 1;
+                        rivalry.0.entry(*b_colony).or_default().avoided += 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_handed_steal_is_an_avoid() {
+        assert_eq!(Outcome::Steal.resolve(false), Outcome::Avoid);
+        assert_eq!(Outcome::Steal.resolve(true), Outcome::Steal);
+        assert_eq!(Outcome::Fight.resolve(false), Outcome::Fight);
+        assert_eq!(Outcome::Avoid.resolve(true), Outcome::Avoid);
+    }
+}
@@ -1,6 +1,9 @@
 use bevy::prelude::*;
 
-use crate::{texture::TextureHandles, world::TimeStep};
+use crate::{texture::TextureHandles, volcano::VolcanoEvent, world::TimeStep};
+
+/// seconds an alert stays on screen
+const ALERT_TIME: f32 = 5.0;
 
 pub enum HudUpdateEvent {
     //PlacementHud(Handle<Image>),
@@ -8,6 +11,8 @@ pub enum HudUpdateEvent {
     TreeMeter(u8),
     DeleteMeter(u8),
     SpeedChange(usize),
+    /// flash a message across the top of the screen
+    Alert(String),
 }
 
 #[derive(Default)]
@@ -15,9 +20,17 @@ pub struct HudContext {
     pub flower_meter: MeterHud,
     pub tree_meter: MeterHud,
     pub delete_meter: MeterHud,
+    pub alert: AlertHud,
     pub hud_hovered: bool,
 }
 
+#[derive(Default)]
+pub struct AlertHud {
+    entity: Option<Entity>,
+    /// runs out when the current alert should be cleared
+    shown: Timer,
+}
+
 #[derive(Default)]
 pub struct MeterHud {
     entity: Option<Entity>,
@@ -124,6 +137,33 @@ pub fn setup(
                     .id(),
             );
 
+            // Alerts
+            hud_ctx.alert.entity = Some(
+                parent
+                    .spawn_bundle(TextBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            position: Rect {
+                                top: Val::Px(10.0),
+                                left: Val::Px(10.0),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        text: Text::with_section(
+                            "",
+                            TextStyle {
+                                font: asset_server.load("fonts/monogram.ttf"),
+                                font_size: 50.0,
+                                color: Color::rgb(1.0, 0.4, 0.1),
+                            },
+                            Default::default(),
+                        ),
+                        ..Default::default()
+                    })
+                    .id(),
+            );
+
             // Timer
             parent
                 .spawn_bundle(NodeBundle {
@@ -266,13 +306,14 @@ pub fn setup(
 }
 
 pub fn hud_update_system(
-    hud_ctx: ResMut<HudContext>,
+    mut hud_ctx: ResMut<HudContext>,
     mut hud_events: EventReader<HudUpdateEvent>,
     texture_handles: Res<TextureHandles>,
     mut qs: QuerySet<(
         QueryState<&mut UiImage>,
         QueryState<(&SpriteIndex, &mut UiImage)>,
     )>,
+    mut texts: Query<&mut Text>,
 ) {
     for event in hud_events.iter() {
         match event {
@@ -324,6 +365,45 @@ pub fn hud_update_system(
                     image.0 = texture_handles[&key].clone();
                 }
             }
+            HudUpdateEvent::Alert(message) => {
+                if let Some(entity) = hud_ctx.alert.entity {
+                    if let Ok(mut text) = texts.get_mut(entity) {
+                        text.sections[0].value = message.clone();
+                    }
+                }
+                hud_ctx.alert.shown = Timer::from_seconds(ALERT_TIME, false);
+            }
+        }
+    }
+}
+
+/// Clear an alert once it has been up for long enough
+pub fn clear_alert(
+    time: Res<Time>,
+    mut hud_ctx: ResMut<HudContext>,
+    mut texts: Query<&mut Text>,
+) {
+    if !hud_ctx.alert.shown.tick(time.delta()).just_finished() {
+        return;
+    }
+    if let Some(entity) = hud_ctx.alert.entity {
+        if let Ok(mut text) = texts.get_mut(entity) {
+            text.sections[0].value.clear();
+        }
+    }
+}
+
+/// Alert the player to eruptions
+pub fn volcano_alert(
+    mut volcano_events: EventReader<VolcanoEvent>,
+    mut hud_events: EventWriter<HudUpdateEvent>,
+) {
+    for event in volcano_events.iter() {
+        if let VolcanoEvent::Erupted(location) = event {
+            hud_events.send(HudUpdateEvent::Alert(format!(
+                "Eruption! Lava is pouring out around {}, {}",
+                location.x, location.y
+            )));
         }
     }
 }
@@ -1,14 +1,18 @@
 mod camera;
+mod director;
 mod draw;
+mod ecology;
 mod game;
 mod grid;
 mod hivemind;
 mod hud;
 mod multivac;
+mod predator;
 mod story;
 mod texture;
 mod ui;
 mod util;
+mod volcano;
 mod world;
 
 use bevy::prelude::*;
@@ -128,6 +132,10 @@ fn main() {
         .add_plugin(draw::Plugin { debug: false })
         .add_plugin(hivemind::Plugin)
         .add_plugin(multivac::Plugin)
+        .add_plugin(director::Plugin)
+        .add_plugin(volcano::Plugin)
+        .add_plugin(predator::Plugin)
+        .add_plugin(ecology::Plugin)
         //.add_startup_system_to_stage(StartupStage::Startup, setup_console_style)
         //.add_plugin(ConsolePlugin) // FIXME: could not work around auto expanding console window,
         //disabling for now
@@ -159,7 +167,12 @@ fn main() {
         .add_system_set(SystemSet::on_update(AppState::Load).with_system(texture::check_textures))
         .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(grid::setup))
         .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(hud::setup))
-        .add_system_set(SystemSet::on_update(AppState::Playing).with_system(hud::hud_update_system))
+        .add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .with_system(hud::hud_update_system)
+                .with_system(hud::clear_alert)
+                .with_system(hud::volcano_alert),
+        )
         .add_system_set(
             SystemSet::on_update(AppState::Playing)
                 .with_system(
@@ -1,37 +1,99 @@
 use crate::{
     draw::{self, ping, ping_long},
-    world::{self, Flag, WorldMap},
+    hivemind::{Forage, Ledger},
+    world::{self, DeRon, Flag, WorldMap, DIRS},
     AppState,
 };
 /// AI implementation for supercomputer civ
-use bevy::{math, prelude::*};
+use bevy::prelude::*;
 use std::collections::{HashMap, VecDeque};
 
-// clockwise, starting north
-const DIRS: [IVec2; 4] = [
-    math::const_ivec2!([0, 1]),
-    math::const_ivec2!([0, -1]),
-    math::const_ivec2!([1, 0]),
-    math::const_ivec2!([-1, 0]),
-];
+/// Delay in seconds before the next multivac state update, picked by the state just entered
+#[derive(Debug, Clone, DeRon)]
+pub struct Clocks {
+    pub init: f32,
+    pub init_route: f32,
+    pub route: f32,
+    pub in_flight: f32,
+    pub search: f32,
+    pub stop: f32,
+    pub error: f32,
+}
+
+impl Clocks {
+    pub fn next(&self, state: MultivacState) -> f32 {
+        match state {
+            MultivacState::Init => self.init,
+            MultivacState::InitRoute(_) => self.init_route,
+            MultivacState::Route(_) => self.route,
+            MultivacState::InFlight => self.in_flight,
+            MultivacState::Search(_) => self.search,
+            MultivacState::Stop => self.stop,
+            MultivacState::Error => self.error,
+        }
+    }
+}
 
+/// A multivac "personality", all timings and expansion policies of a multivac network
+#[derive(Debug, Clone, DeRon)]
 pub struct Config {
-    location: IVec2,
-    route_clock: f32, // in seconds
-    gather_clock: f32,
-    route_delay: f32,
-    gather_rate: u32,
+    pub name: String,
+    pub route_clock: f32, // in seconds
+    pub gather_clock: f32,
+    pub route_delay: f32,
+    pub gather_rate: u32,
+    pub clocks: Clocks,
+    /// fraction of the search frontier expanded every search tick
+    pub search_budget: f32,
+    /// targets further than this many tiles from the network are never picked
+    pub max_route_length: usize,
+    /// depleted outposts become new multivacs
+    pub promote_depleted: bool,
+    pub eat_flowers: bool,
+    pub eat_trees: bool,
+    pub eat_volcanoes: bool,
+    /// if false, a multivac that has run out of things to search goes dormant
+    pub restart_on_stop: bool,
+}
+
+/// All available multivac personalities, and the one selected for this scenario
+#[derive(DeRon)]
+pub struct Personalities {
+    pub selected: String,
+    pub personalities: Vec<Config>,
 }
 
 impl Config {
-    pub fn default() -> Self {
-        Self {
-            location: IVec2::splat((crate::WORLD_SIZE * 3 / 4) as i32),
-            route_clock: 0.1,
-            gather_clock: 1.0,
-            route_delay: 0.3,
-            gather_rate: 50,
+    // TODO: remove assets hardcoded path
+    pub fn load() -> Self {
+        let bytes = include_str!("../../assets/multivac.ron");
+        let personalities: Personalities = DeRon::deserialize_ron(bytes)
+            .expect("ERROR: failed to load multivac personalities");
+        personalities
+            .personalities
+            .into_iter()
+            .find(|p| p.name == personalities.selected)
+            .unwrap_or_else(|| {
+                panic!(
+                    "ERROR: multivac personality '{}' not found",
+                    personalities.selected
+                )
+            })
+    }
+
+    /// tiles this multivac will connect outposts to
+    pub fn food(&self) -> Flag {
+        let mut food = Flag::EMPTY;
+        if self.eat_flowers {
+            food |= Flag::FLOWER;
+        }
+        if self.eat_trees {
+            food |= Flag::TREE;
+        }
+        if self.eat_volcanoes {
+            food |= Flag::VOLCANO;
         }
+        food
     }
 }
 
@@ -41,11 +103,17 @@ pub struct SearchPath {
     // validity
     pub pos: IVec2,
     pub prev: IVec2,
+    /// tiles away from the network
+    pub length: usize,
 }
 
 impl SearchPath {
-    pub fn new(current: IVec2, prev: IVec2) -> Self {
-        Self { pos: current, prev }
+    pub fn new(current: IVec2, prev: IVec2, length: usize) -> Self {
+        Self {
+            pos: current,
+            prev,
+            length,
+        }
     }
 }
 
@@ -101,6 +169,93 @@ pub enum MultivacState {
 #[derive(Component)]
 pub struct Clock(pub Timer);
 
+/// Sent when a wire of the multivac network is cut at this location
+pub struct WireCutEvent(pub IVec2);
+
+/// Sent when an outpost takes the last of the food on its tile
+pub struct HarvestEvent {
+    pub pos: IVec2,
+    /// the kinds of food that were used up
+    pub kind: Flag,
+}
+
+/// Take up to `rate` food off an outpost's tile. Returns the food kinds once the last of them is
+/// gone
+pub fn graze(cell: &mut Flag, rate: u32) -> Option<Flag> {
+    let resource = cell.get_resource_quantity();
+    cell.set_resource_quantity(resource - resource.min(rate));
+    if resource > rate {
+        return None;
+    }
+    let kind = *cell & Flag::MULTIVAC_FOOD;
+    *cell &= !Flag::MULTIVAC_FOOD;
+    Some(kind)
+}
+
+/// Pull an outpost off its tile, leaving whatever else is there
+pub fn vacate(cell: &mut Flag) {
+    *cell &= !(Flag::OUTPOST | Flag::CONNECTED | Flag::WIRE);
+}
+
+/// Where a depleted outpost's multivac goes: on the outpost's own tile if the harvest left nothing
+/// there, otherwise on open ground next to it
+fn promotion_site(map: &WorldMap, pos: IVec2) -> Option<IVec2> {
+    let mut left = map[pos];
+    vacate(&mut left);
+    if (left & Flag::KIND_MASK).is_empty() {
+        return Some(pos);
+    }
+    DIRS.iter()
+        .map(|dir| pos + *dir)
+        .find(|p| matches!(map.get_ivec2(*p), Some(flags) if flags.is_empty()))
+}
+
+/// Shared brain of every multivac. Multivacs wired together form a single network that searches
+/// as one from all of its tiles, led by one of its multivacs. Separate networks claim their
+/// targets here so they never chase the same food
+#[derive(Default)]
+pub struct Overmind {
+    /// target tile, and the network leader that claimed it
+    claims: HashMap<IVec2, Entity>,
+    /// every multivac, and the leader of the network it belongs to
+    leaders: HashMap<Entity, Entity>,
+    /// tiles of each network, keyed by leader
+    networks: HashMap<Entity, Vec<IVec2>>,
+}
+
+impl Overmind {
+    /// leader of the network this multivac belongs to, unknown multivacs lead themselves
+    pub fn leader(&self, multivac: Entity) -> Entity {
+        *self.leaders.get(&multivac).unwrap_or(&multivac)
+    }
+
+    pub fn network(&self, leader: Entity) -> &[IVec2] {
+        match self.networks.get(&leader) {
+            Some(network) => network,
+            None => &[],
+        }
+    }
+
+    /// Whether any network still counts a tile that lost its wire, outpost or multivac, to lava,
+    /// an outpost moving on, or anything else that took it off the map
+    fn stale(&self, map: &WorldMap) -> bool {
+        self.networks
+            .values()
+            .flatten()
+            .any(|pos| !map[*pos].intersects(Flag::WIRE | Flag::OUTPOST | Flag::MULTIVAC))
+    }
+
+    /// claim a target for a network, fails if another network got there first
+    pub fn claim(&mut self, target: IVec2, leader: Entity) -> bool {
+        *self.claims.entry(target).or_insert(leader) == leader
+    }
+
+    /// give up on a target so any network can try it again
+    pub fn release(&mut self, target: IVec2) {
+        self.claims.remove(&target);
+    }
+}
+
 #[derive(Component, Debug)]
 pub struct Multivac {
     /// Origin of the multivac network
@@ -121,35 +276,42 @@ impl Multivac {
         multivac
     }
 
-    pub fn init(&mut self, map: &WorldMap) -> MultivacState {
+    /// Restart the search from every tile of the network. An empty network searches from the
+    /// origin alone
+    pub fn init(&mut self, map: &WorldMap, network: &[IVec2]) -> MultivacState {
         self.visited.clear();
         self.queue.clear();
-        // All of origin's neighbors go into the queue
-        self.visited.insert(self.origin, IVec2::ZERO);
-        for dir in DIRS {
-            let neighbor = self.origin + dir;
-            match map.get_ivec2(neighbor) {
-                Some(_) => {
-                    self.queue.push_back(SearchPath::new(neighbor, self.origin));
-                    // search twice to get neighbor of neighbors
-                    for dir in DIRS {
-                        let neighbor_2 = self.origin + dir;
-                        self.queue
-                            .push_back(SearchPath::new(neighbor_2, self.origin))
+        let origin = [self.origin];
+        let sources = if network.is_empty() { &origin[..] } else { network };
+        // sources point back at themselves, all of their neighbors go into the queue
+        for source in sources {
+            self.visited.insert(*source, *source);
+        }
+        for source in sources {
+            for dir in DIRS {
+                let neighbor = *source + dir;
+                match map.get_ivec2(neighbor) {
+                    Some(_) if !self.visited.contains_key(&neighbor) => {
+                        self.queue.push_back(SearchPath::new(neighbor, *source, 1));
                     }
+                    _ => continue,
                 }
-                None => continue,
             }
         }
         MultivacState::Search(None)
     }
 
+    /// is this tile part of the network the current search started from
+    pub fn is_source(&self, pos: IVec2) -> bool {
+        self.visited.get(&pos) == Some(&pos)
+    }
+
     /// search routine for a single clock cycle
     // NOTE: This flavor of BFS is ordered as such:
     //  1. Attempt to 'visit' cell
     //  2. Early exit if it is our destination OR if it is
     //  3. Actually add to visited list and then enqueue neighbors
-    pub fn search(&mut self, map: &WorldMap) -> MultivacState {
+    pub fn search(&mut self, map: &WorldMap, food: Flag, max_length: usize) -> MultivacState {
         let candidate = match self.queue.pop_front() {
             Some(v) => v,
             None => return MultivacState::Stop, // early exit path if queue is empty
@@ -166,22 +328,40 @@ impl Multivac {
             None => return MultivacState::Search(None),
         };
 
+        // outposts cut off from the network can be rerouted
+        if flags.intersects(Flag::OUTPOST) && !flags.intersects(Flag::CONNECTED) {
+            return MultivacState::InitRoute(candidate.pos);
+        }
+
         // currently on a non-empty tile, can't visit
         if flags.intersects(!Flag::MULTIVAC_FOOD) {
             return MultivacState::Search(None);
         }
 
-        if flags.intersects(Flag::MULTIVAC_FOOD) {
+        if flags.intersects(food) {
             // things multivac can connect to
             return MultivacState::InitRoute(candidate.pos);
         }
+
+        if flags.intersects(Flag::MULTIVAC_FOOD) {
+            // food this multivac doesn't care for, still blocks the route
+            return MultivacState::Search(None);
+        }
+
+        // anything further out would be too far to route to
+        if candidate.length >= max_length {
+            return MultivacState::Search(Some(candidate.pos));
+        }
         for dir in DIRS {
             let neighbor = candidate.pos + dir;
             match map.get_ivec2(neighbor) {
                 Some(_) => {
                     if !self.visited.contains_key(&neighbor) {
-                        self.queue
-                            .push_back(SearchPath::new(neighbor, candidate.pos))
+                        self.queue.push_back(SearchPath::new(
+                            neighbor,
+                            candidate.pos,
+                            candidate.length + 1,
+                        ))
                     }
                     {}
                 }
@@ -192,7 +372,12 @@ impl Multivac {
     }
 
     /// search with the opposite iteration order over the neighbors
-    pub fn search_reverse(&mut self, map: &WorldMap) -> MultivacState {
+    pub fn search_reverse(
+        &mut self,
+        map: &WorldMap,
+        food: Flag,
+        max_length: usize,
+    ) -> MultivacState {
         let candidate = match self.queue.pop_front() {
             Some(v) => v,
             None => return MultivacState::Stop, // early exit path if queue is empty
@@ -209,23 +394,41 @@ impl Multivac {
             None => return MultivacState::Search(None),
         };
 
+        // outposts cut off from the network can be rerouted
+        if flags.intersects(Flag::OUTPOST) && !flags.intersects(Flag::CONNECTED) {
+            return MultivacState::InitRoute(candidate.pos);
+        }
+
         // currently on a non-empty tile, can't visit
         if flags.intersects(!Flag::MULTIVAC_FOOD & Flag::KIND_MASK) {
             return MultivacState::Search(None);
         }
 
-        if flags.intersects(Flag::MULTIVAC_FOOD) {
+        if flags.intersects(food) {
             // things multivac can connect to
             return MultivacState::InitRoute(candidate.pos);
         }
 
+        if flags.intersects(Flag::MULTIVAC_FOOD) {
+            // food this multivac doesn't care for, still blocks the route
+            return MultivacState::Search(None);
+        }
+
+        // anything further out would be too far to route to
+        if candidate.length >= max_length {
+            return MultivacState::Search(Some(candidate.pos));
+        }
+
         for dir in DIRS.iter().rev() {
             let neighbor = candidate.pos + *dir;
             match map.get_ivec2(neighbor) {
                 Some(_) => {
                     if !self.visited.contains_key(&neighbor) {
-                        self.queue
-                            .push_back(SearchPath::new(neighbor, candidate.pos))
+                        self.queue.push_back(SearchPath::new(
+                            neighbor,
+                            candidate.pos,
+                            candidate.length + 1,
+                        ))
                     }
                     {}
                 }
@@ -252,7 +455,7 @@ pub fn setup(
         map[position.0] |= Flag::MULTIVAC;
 
         for dir in DIRS.iter().rev() {
-            let neighbor = config.location + *dir;
+            let neighbor = position.0.as_ivec2() + *dir;
             match map.get_ivec2(neighbor) {
                 Some(_) => {
                     commands
@@ -274,24 +477,36 @@ pub fn update(
     map_res: ResMut<WorldMap>,
     time_step: Res<world::TimeStep>,
     config: Res<Config>,
-    mut multivac_query: Query<(&mut Multivac, &mut Clock, &mut MultivacState)>,
+    overmind: ResMut<Overmind>,
+    mut multivac_query: Query<(Entity, &mut Multivac, &mut Clock, &mut MultivacState)>,
 ) {
     let map = map_res.into_inner();
+    let overmind = overmind.into_inner();
 
     let time_step = time_step.into_inner();
-    for (mut multivac, mut clock, state) in multivac_query.iter_mut() {
+    for (entity, mut multivac, mut clock, mut state) in multivac_query.iter_mut() {
         if clock.0.tick(time_step.into()).just_finished() {
+            // only the leader of a network searches, everyone else waits to lead a network again
+            if overmind.leader(entity) != entity {
+                *state = MultivacState::Init;
+                clock.0 = Timer::from_seconds(config.clocks.init, false);
+                continue;
+            }
+
             let state = state.into_inner();
             *state = match state {
-                MultivacState::Init => multivac.init(map),
+                MultivacState::Init => multivac.init(map, overmind.network(entity)),
                 MultivacState::InitRoute(p) => {
-                    map[*p] |= Flag::OUTPOST;
                     ping(&mut commands, *p);
-                    commands
-                        .spawn()
-                        .insert(world::Outpost)
-                        .insert(Clock(Timer::from_seconds(config.gather_clock, true)))
-                        .insert(world::Position(p.as_vec2()));
+                    // disconnected outposts only need a new route, not a new outpost
+                    if !map[*p].intersects(Flag::OUTPOST) {
+                        map[*p] |= Flag::OUTPOST;
+                        commands
+                            .spawn()
+                            .insert(world::Outpost)
+                            .insert(Clock(Timer::from_seconds(config.gather_clock, true)))
+                            .insert(world::Position(p.as_vec2()));
+                    }
                     MultivacState::Route(*p)
                 }
                 // route all in one tick to prevent pathing errors from player clicks
@@ -299,7 +514,6 @@ pub fn update(
                     // backtrack by following visited, place down wires. If prev somehow isn't in visited, early exit
                     let mut state = MultivacState::InFlight;
                     let mut prev = *p;
-                    // punch out if we are taking too long
                     let mut distance_count = 0;
                     while state == MultivacState::InFlight {
                         distance_count += 1;
@@ -310,6 +524,11 @@ pub fn update(
                                 break;
                             }
                         };
+                        // reached the network, connection complete
+                        if multivac.is_source(pos) {
+                            state = MultivacState::Stop;
+                            break;
+                        }
                         let next = match multivac.visited.get(&pos) {
                             Some(p) => *p,
                             None => {
@@ -325,32 +544,17 @@ pub fn update(
                                 break;
                             }
                         };
-                        let next_flags = match map.get_ivec2(pos) {
-                            Some(f) => f,
-                            None => {
-                                state = MultivacState::Error;
-                                break;
-                            }
-                        };
 
-                        if next_flags.intersects(Flag::MULTIVAC) {
-                            state = MultivacState::Stop;
-                        }
                         if !pos_flags.is_empty() {
                             // map changed since searched
                             state = MultivacState::Error;
                             break;
                         };
-                        if distance_count >= crate::WORLD_SIZE * crate::WORLD_SIZE {
-                            state = MultivacState::Error;
-                            break;
-                        }
 
                         map[pos] |= Flag::WIRE; // @NOTE: pos already checked above to get flags
                         commands
                             .spawn()
                             .insert(world::Wire)
-                            .insert(world::Wire)
                             .insert(WireKind::from_route(prev, pos, next))
                             .insert(world::Position(pos.as_vec2()))
                             .insert(draw::Delay(Timer::from_seconds(
@@ -360,43 +564,51 @@ pub fn update(
                         ping_long(&mut commands, pos);
                         prev = pos;
                     }
+                    // a failed route frees its target for whichever network gets there next
+                    if state == MultivacState::Error {
+                        overmind.release(*p);
+                    }
                     state
                 }
                 MultivacState::InFlight => MultivacState::Error, // should not be caught in flight between clock cycles
                 MultivacState::Search(_) => {
                     let mut state = MultivacState::Stop;
-                    for _ in 0..multivac.queue.len() / 2 {
-                        state = multivac.search(map);
+                    let food = config.food();
+                    let budget = (multivac.queue.len() as f32 * config.search_budget) as usize;
+                    for _ in 0..budget {
+                        state = multivac.search(map, food, config.max_route_length);
                         match state {
                             MultivacState::Search(Some(p)) => ping(&mut commands, p),
                             MultivacState::Search(None) => {}
+                            // another network already claimed this target, keep looking
+                            MultivacState::InitRoute(p) if !overmind.claim(p, entity) => {
+                                state = MultivacState::Search(None)
+                            }
                             _ => break,
                         };
 
-                        state = multivac.search_reverse(map);
+                        state = multivac.search_reverse(map, food, config.max_route_length);
                         match state {
                             MultivacState::Search(Some(p)) => ping(&mut commands, p),
                             MultivacState::Search(None) => {}
+                            MultivacState::InitRoute(p) if !overmind.claim(p, entity) => {
+                                state = MultivacState::Search(None)
+                            }
                             _ => break,
                         };
                     }
                     state
                 }
-                MultivacState::Stop => multivac.init(map), // TODO: placeholder
-                MultivacState::Error => multivac.init(map), //TODO: placeholder
+                // nothing left to find, either start over or go dormant
+                MultivacState::Stop if config.restart_on_stop => {
+                    multivac.init(map, overmind.network(entity))
+                }
+                MultivacState::Stop => MultivacState::Stop,
+                // the map changed under a route, search again from scratch
+                MultivacState::Error => multivac.init(map, overmind.network(entity)),
             };
 
-            // TODO: put this in config
-            let next_clock = match state {
-                MultivacState::Init => 50.0,
-                MultivacState::InitRoute(_) => 0.0,
-                MultivacState::Route(_) => 0.5,
-                MultivacState::InFlight => 0.1,
-                MultivacState::Search(_) => 0.1,
-                MultivacState::Stop => 50.0,
-                MultivacState::Error => 50.0,
-            };
-            clock.0 = Timer::from_seconds(next_clock, false);
+            clock.0 = Timer::from_seconds(config.clocks.next(*state), false);
         }
     }
 }
@@ -405,7 +617,10 @@ pub fn promote_outpost(
     mut commands: Commands,
     time_step: Res<world::TimeStep>,
     config: Res<Config>,
+    forage: Res<Forage>,
     mut map: ResMut<WorldMap>,
+    mut ledger: ResMut<Ledger>,
+    mut harvests: EventWriter<HarvestEvent>,
     mut query: Query<(Entity, &mut Clock, &world::Position), With<world::Outpost>>,
 ) {
     let time_step = time_step.into_inner();
@@ -416,40 +631,145 @@ pub fn promote_outpost(
                 continue;
             }
 
-            // if food depleted, become a multivac spawner
-            if !map[pos.0].intersects(Flag::MULTIVAC_FOOD) {
+            // outposts cut off from the network stop gathering until they are rerouted
+            if !map[pos.0].intersects(Flag::CONNECTED) {
+                continue;
+            }
+            let tile = pos.0.as_ivec2();
+            let before = forage.on_tile(map[tile]);
+
+            // if food depleted, leave, becoming a multivac spawner if the personality promotes
+            if !map[tile].intersects(Flag::MULTIVAC_FOOD) {
                 commands.entity(entity).despawn();
-                map[pos.0] = Flag::MULTIVAC; // the hard assignment is meant to remove the outpost flag
-                commands
-                    .spawn()
-                    .insert(world::Multivac)
-                    .insert(world::Position(pos.0));
-                draw::ping_long(&mut commands, pos.0.as_ivec2());
+                let site = promotion_site(&map, tile);
+                vacate(&mut map[tile]);
+                if let Some(site) = site.filter(|_| config.promote_depleted) {
+                    map[site] = Flag::MULTIVAC;
+                    commands
+                        .spawn()
+                        .insert(world::Multivac)
+                        .insert(world::Position(site.as_vec2()));
+                    draw::ping_long(&mut commands, site);
+                }
             // eat the food
-            } else {
-                let resource = map[pos.0].get_resource_quantity();
-
-                if resource == 0 {
-                    map[pos.0] &= !Flag::MULTIVAC_FOOD;
-                } else {
-                    map[pos.0].set_resource_quantity(
-                        resource - std::cmp::min(resource, config.gather_rate),
-                    );
+            } else if let Some(kind) = graze(&mut map[tile], config.gather_rate) {
+                harvests.send(HarvestEvent { pos: tile, kind });
+            }
+            ledger.retile(before, forage.on_tile(map[tile]));
+        }
+    }
+}
+
+/// Flood the network from every multivac over wires, outposts and other multivacs, flagging every
+/// reachable tile as connected. Multivacs reached by the same flood are merged into one network.
+/// Only reruns when the network has grown, or lost a tile
+pub fn update_connections(
+    map: ResMut<WorldMap>,
+    overmind: ResMut<Overmind>,
+    mut wire_cuts: EventReader<WireCutEvent>,
+    added_wires: Query<(), Added<world::Wire>>,
+    added_outposts: Query<(), Added<world::Outpost>>,
+    added_multivacs: Query<(), Added<world::Multivac>>,
+    multivacs: Query<(Entity, &world::Position), With<world::Multivac>>,
+) {
+    let cut = wire_cuts.iter().count() > 0;
+    if !cut
+        && !overmind.stale(&map)
+        && added_wires.iter().next().is_none()
+        && added_outposts.iter().next().is_none()
+        && added_multivacs.iter().next().is_none()
+    {
+        return;
+    }
+    let map = map.into_inner();
+    let overmind = overmind.into_inner();
+
+    for y in 0..map.h() {
+        for x in 0..map.w() {
+            map[y][x] &= !Flag::CONNECTED;
+        }
+    }
+
+    overmind.leaders.clear();
+    overmind.networks.clear();
+    let origins: HashMap<IVec2, Entity> = multivacs
+        .iter()
+        .map(|(entity, position)| (position.0.as_ivec2(), entity))
+        .collect();
+    let mut queue: VecDeque<IVec2> = VecDeque::new();
+    for (leader, position) in multivacs.iter() {
+        // already merged into an earlier network
+        if overmind.leaders.contains_key(&leader) {
+            continue;
+        }
+
+        let mut network = Vec::new();
+        queue.push_back(position.0.as_ivec2());
+        while let Some(pos) = queue.pop_front() {
+            match map.get_ivec2(pos) {
+                Some(f) if !f.intersects(Flag::CONNECTED) => map[pos] |= Flag::CONNECTED,
+                _ => continue,
+            }
+            network.push(pos);
+            if let Some(member) = origins.get(&pos) {
+                overmind.leaders.insert(*member, leader);
+            }
+            for dir in DIRS {
+                let neighbor = pos + dir;
+                if let Some(f) = map.get_ivec2(neighbor) {
+                    if f.intersects(Flag::WIRE | Flag::OUTPOST | Flag::MULTIVAC)
+                        && !f.intersects(Flag::CONNECTED)
+                    {
+                        queue.push_back(neighbor);
+                    }
                 }
             }
         }
+        overmind.networks.insert(leader, network);
     }
+
+    // connected targets are done, and claims only hold while their network does
+    let leaders = &overmind.leaders;
+    overmind.claims.retain(|target, leader| {
+        !map[*target].intersects(Flag::CONNECTED) && leaders.get(leader) == Some(leader)
+    });
 }
 
 pub struct Plugin;
 
 impl bevy::prelude::Plugin for Plugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Config::default()).add_system_set(
-            SystemSet::on_update(AppState::Playing)
-                .with_system(setup)
-                .with_system(update)
-                .with_system(promote_outpost),
-        );
+        app.insert_resource(Config::load())
+            .init_resource::<Overmind>()
+            .add_event::<WireCutEvent>()
+            .add_event::<HarvestEvent>()
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(setup)
+                    .with_system(update)
+                    .with_system(update_connections)
+                    .with_system(promote_outpost),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_goes_stale_when_a_tile_leaves_the_map() {
+        let mut map = WorldMap::new();
+        let mut overmind = Overmind::default();
+        let (multivac, outpost) = (IVec2::new(5, 5), IVec2::new(6, 5));
+        map[multivac] = Flag::MULTIVAC | Flag::CONNECTED;
+        map[outpost] = Flag::TREE | Flag::OUTPOST | Flag::CONNECTED;
+        overmind
+            .networks
+            .insert(Entity::from_raw(0), vec![multivac, outpost]);
+        assert!(!overmind.stale(&map));
+
+        vacate(&mut map[outpost]);
+        assert!(overmind.stale(&map));
     }
 }
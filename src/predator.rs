@@ -0,0 +1,388 @@
+/// Predators. Hornets nest in trees and rock, fly out to hunt drones by following the density
+/// field, and eat any they catch. Guards can fight them off, and every kill raises the alarm
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+use std::time::Duration;
+
+use crate::{
+    hivemind::{
+        alarm::{self, AlarmEvent},
+        caste::{self, Caste, Threat, Threats},
+        colony,
+        field_systems,
+        neighbors::SpatialHash,
+        Deaths, Drone, ScalarField,
+    },
+    world::{self, Flag, WorldMap, DIRS},
+    AppState,
+};
+
+#[derive(Debug)]
+pub struct Config {
+    /// the real-time period between spawn attempts
+    pub spawn_clock: f32,
+    /// tile kinds hornets nest in
+    pub lairs: Flag,
+    /// chance any one lair tile releases a hornet each attempt
+    pub spawn_chance: f64,
+    /// most hornets in the world at once
+    pub max: usize,
+    pub move_speed: f32,
+    pub turn_speed: f32,
+    pub chaos: f32,
+    /// density gradient strong enough for a hornet to follow
+    pub scent: f32,
+    /// how close a drone has to be to get caught
+    pub reach: f32,
+    /// drones a hornet eats before flying home
+    pub appetite: u32,
+    /// seconds spent eating each drone
+    pub feed_time: f32,
+    /// seconds a hornet hunts without a catch before giving up
+    pub patience: f32,
+    /// seconds a hornet rests in its lair between hunts
+    pub rest: f32,
+    /// chance a guard within reach kills the hornet instead of getting caught
+    pub guard_odds: f64,
+}
+
+impl Config {
+    pub fn default() -> Self {
+        Self {
+            spawn_clock: 10.0,
+            lairs: Flag::TREE | Flag::ROCK,
+            spawn_chance: 0.0005,
+            max: 6,
+            move_speed: 1.4,
+            turn_speed: 0.1,
+            chaos: 0.5,
+            scent: 0.05,
+            reach: 0.5,
+            appetite: 3,
+            feed_time: 5.0,
+            patience: 60.0,
+            rest: 30.0,
+            guard_odds: 0.3,
+        }
+    }
+}
+
+/// Time until hornets next try to spawn
+pub struct SpawnClock(pub Timer);
+
+#[derive(Component)]
+pub struct Predator {
+    /// where the hornet rests between hunts
+    pub lair: Vec2,
+    pub direction: Vec2,
+    /// drones eaten this hunt
+    pub eaten: u32,
+    /// runs out when the current state is done
+    pub timer: Timer,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PredatorState {
+    Lurking,
+    Hunting,
+    Feeding,
+    Returning,
+}
+
+impl Predator {
+    /// Move on once the current state is done, and pick which way to fly, if anywhere. `scent` is
+    /// the density gradient where the hornet is
+    fn think(
+        &mut self,
+        state: &mut PredatorState,
+        config: &Config,
+        dt: Duration,
+        pos: Vec2,
+        scent: Vec2,
+    ) -> Option<Vec2> {
+        let done = self.timer.tick(dt).finished();
+        match *state {
+            PredatorState::Lurking => {
+                if done {
+                    *state = PredatorState::Hunting;
+                    self.timer = Timer::from_seconds(config.patience, false);
+                }
+                None
+            }
+            PredatorState::Feeding => {
+                if done && self.eaten >= config.appetite {
+                    *state = PredatorState::Returning;
+                } else if done {
+                    *state = PredatorState::Hunting;
+                    self.timer = Timer::from_seconds(config.patience, false);
+                }
+                None
+            }
+            PredatorState::Returning => {
+                if pos.distance(self.lair) < 1.0 {
+                    *state = PredatorState::Lurking;
+                    self.eaten = 0;
+                    self.timer = Timer::from_seconds(config.rest, false);
+                    None
+                } else {
+                    Some(self.lair - pos)
+                }
+            }
+            PredatorState::Hunting if done => {
+                *state = PredatorState::Returning;
+                None
+            }
+            // up the density gradient where there's a scent, wandering where there isn't
+            PredatorState::Hunting => {
+                if scent.length() >= config.scent {
+                    Some(scent)
+                } else {
+                    Some(self.direction)
+                }
+            }
+        }
+    }
+
+    /// Start eating a drone just caught
+    fn feed(&mut self, state: &mut PredatorState, config: &Config) {
+        self.eaten += 1;
+        self.timer = Timer::from_seconds(config.feed_time, false);
+        *state = PredatorState::Feeding;
+    }
+}
+
+/// Whether the drone a hornet reached kills it instead of getting caught
+fn fights_off(caste: Caste, config: &Config, rng: &mut impl Rng) -> bool {
+    caste == Caste::Guard && rng.gen_bool(config.guard_odds)
+}
+
+/// Lair tiles now and then release a hornet onto open ground next to them
+pub fn spawn(
+    mut commands: Commands,
+    time_step: Res<world::TimeStep>,
+    map: Res<WorldMap>,
+    config: Res<Config>,
+    mut clock: ResMut<SpawnClock>,
+    predators: Query<&Predator>,
+) {
+    if !clock.0.tick(time_step.into_inner().into()).just_finished() {
+        return;
+    }
+
+    let mut rng = SmallRng::from_entropy();
+    let mut count = predators.iter().count();
+    for y in 0..map.h() {
+        for x in 0..map.w() {
+            if count >= config.max {
+                return;
+            }
+            if !map[y][x].intersects(config.lairs) || !rng.gen_bool(config.spawn_chance) {
+                continue;
+            }
+            let tile = IVec2::new(x as i32, y as i32);
+            let open = DIRS
+                .iter()
+                .map(|dir| tile + *dir)
+                .find(|t| matches!(map.get_ivec2(*t), Some(flags) if flags.is_empty()));
+            if let Some(open) = open {
+                let lair = open.as_vec2() + Vec2::new(0.5, 0.5);
+                commands
+                    .spawn()
+                    .insert(Predator {
+                        lair,
+                        direction: Vec2::ZERO,
+                        eaten: 0,
+                        timer: Timer::from_seconds(config.patience, false),
+                    })
+                    .insert(PredatorState::Hunting)
+                    .insert(world::Position(lair));
+                count += 1;
+                info!("a hornet flew out of {}", tile);
+            }
+        }
+    }
+}
+
+/// Hornets follow the density field to drones, eat what they catch, and rest once full or tired
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn hunt(
+    mut commands: Commands,
+    time_step: Res<world::TimeStep>,
+    map: Res<WorldMap>,
+    config: Res<Config>,
+    alarm: Res<alarm::Config>,
+    castes: Res<caste::Config>,
+    hash: Res<SpatialHash>,
+    mut deaths: ResMut<Deaths>,
+    mut threats: ResMut<Threats>,
+    mut alarms: EventWriter<AlarmEvent>,
+    density_field: Query<&ScalarField, With<field_systems::Density>>,
+    drones: Query<&Caste, With<Drone>>,
+    mut predators: Query<(Entity, &mut Predator, &mut PredatorState, &mut world::Position)>,
+) {
+    let time_step = time_step.into_inner();
+    let density_f = density_field.single();
+    let mut rng = SmallRng::from_entropy();
+
+    for (entity, mut predator, mut state, mut pos) in predators.iter_mut() {
+        let scent = density_f.grad(pos.0);
+        let signal = predator.think(&mut state, &config, time_step.into(), pos.0, scent);
+
+        if let Some(mut signal) = signal {
+            signal = signal.normalize_or_zero();
+            signal.x += rng.gen_range(-config.chaos..config.chaos);
+            signal.y += rng.gen_range(-config.chaos..config.chaos);
+            predator.direction = predator
+                .direction
+                .lerp(signal.normalize_or_zero(), config.turn_speed)
+                .normalize_or_zero()
+                * config.move_speed;
+            pos.0 = map.sweep(map.escape(pos.0), *time_step * predator.direction);
+        }
+
+        if *state != PredatorState::Hunting {
+            continue;
+        }
+        let prey = hash
+            .near(entity, pos.0, config.reach)
+            .find(|n| !deaths.dead(n.drone))
+            .copied();
+        let prey = match prey {
+            Some(prey) => prey,
+            None => continue,
+        };
+        let caste = match drones.get(prey.drone) {
+            Ok(caste) => caste,
+            Err(_) => continue,
+        };
+        threats.0.push(Threat {
+            pos: prey.pos,
+            colony: prey.colony,
+            left: castes.alarm,
+        });
+
+        if fights_off(*caste, &config, &mut rng) {
+            commands.entity(entity).despawn();
+            alarms.send(AlarmEvent {
+                pos: prey.pos,
+                strength: alarm.encounter,
+            });
+            info!("a guard of colony {:?} killed a hornet at {}", prey.colony, pos.0);
+            continue;
+        }
+        deaths.kill(prey.drone);
+        predator.feed(&mut state, &config);
+        debug!("a hornet caught a drone of colony {:?} at {}", prey.colony, prey.pos);
+    }
+}
+
+/// Face hornet sprites the way they're flying
+pub fn update_sprites(mut query: Query<(&Predator, &mut TextureAtlasSprite)>) {
+    for (predator, mut sprite) in query.iter_mut() {
+        let idx = if colony::faces_left(predator.direction) { 1 } else { 0 };
+        if idx != sprite.index {
+            sprite.index = idx;
+        }
+    }
+}
+
+pub struct Plugin;
+
+impl bevy::prelude::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        let config = Config::default();
+        app.insert_resource(SpawnClock(Timer::from_seconds(config.spawn_clock, true)))
+            .insert_resource(config)
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .after(world::Order::EntityUpdate)
+                    .with_system(spawn)
+                    .with_system(hunt)
+                    .with_system(update_sprites),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(s: f32) -> Duration {
+        Duration::from_secs_f32(s)
+    }
+
+    #[test]
+    fn hornet_hunts_feeds_returns_and_rests() {
+        let config = Config {
+            appetite: 2,
+            ..Config::default()
+        };
+        let lair = Vec2::new(5.5, 5.5);
+        let away = lair + Vec2::new(10.0, 0.0);
+        let mut predator = Predator {
+            lair,
+            direction: Vec2::X,
+            eaten: 0,
+            timer: Timer::from_seconds(config.rest, false),
+        };
+        let mut state = PredatorState::Lurking;
+
+        // rests out its time in the lair, then hunts up the scent
+        predator.think(&mut state, &config, seconds(config.rest), lair, Vec2::ZERO);
+        assert_eq!(state, PredatorState::Hunting);
+        let scent = Vec2::new(0.0, 1.0);
+        let signal = predator.think(&mut state, &config, seconds(1.0), away, scent);
+        assert_eq!(signal, Some(scent));
+
+        // still hungry after the first catch, full after the second
+        predator.feed(&mut state, &config);
+        assert_eq!(state, PredatorState::Feeding);
+        predator.think(&mut state, &config, seconds(config.feed_time), away, scent);
+        assert_eq!(state, PredatorState::Hunting);
+        predator.feed(&mut state, &config);
+        predator.think(&mut state, &config, seconds(config.feed_time), away, scent);
+        assert_eq!(state, PredatorState::Returning);
+
+        // flies home, and rests once there
+        let signal = predator.think(&mut state, &config, seconds(1.0), away, scent);
+        assert_eq!(signal, Some(lair - away));
+        predator.think(&mut state, &config, seconds(1.0), lair, scent);
+        assert_eq!(state, PredatorState::Lurking);
+        assert_eq!(predator.eaten, 0);
+    }
+
+    #[test]
+    fn hornet_gives_up_a_fruitless_hunt() {
+        let config = Config::default();
+        let mut predator = Predator {
+            lair: Vec2::ZERO,
+            direction: Vec2::X,
+            eaten: 0,
+            timer: Timer::from_seconds(config.patience, false),
+        };
+        let mut state = PredatorState::Hunting;
+        let pos = Vec2::new(10.0, 10.0);
+        let signal = predator.think(&mut state, &config, seconds(1.0), pos, Vec2::ZERO);
+        assert_eq!(signal, Some(Vec2::X));
+        predator.think(&mut state, &config, seconds(config.patience), pos, Vec2::ZERO);
+        assert_eq!(state, PredatorState::Returning);
+    }
+
+    #[test]
+    fn only_guards_fight_hornets_off() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let sure = Config {
+            guard_odds: 1.0,
+            ..Config::default()
+        };
+        let never = Config {
+            guard_odds: 0.0,
+            ..Config::default()
+        };
+        assert!(fights_off(Caste::Guard, &sure, &mut rng));
+        assert!(!fights_off(Caste::Guard, &never, &mut rng));
+        assert!(!fights_off(Caste::Forager, &sure, &mut rng));
+        assert!(!fights_off(Caste::Scout, &sure, &mut rng));
+    }
+}
@@ -8,6 +8,7 @@ use bevy_console::PrintConsoleLine;
 use bevy_console::{reply, AddConsoleCommand, ConsoleCommand, ConsoleConfiguration, ConsolePlugin};
 
 use crate::game;
+use crate::volcano::VolcanoEvent;
 
 /// trigger the next story element
 pub struct Trigger(pub usize);
@@ -19,7 +20,7 @@ const INFO: &'static str = "To control the ship:
  - [Z], [X], and [SCROLL WHEEL] control the camera zoom
  - [MOUSE1] to place flowers on the map
  - [MOUSE2] to place trees on the map
- - [MOUSE3] to remove a flower or tree from the map
+ - [MOUSE3] to remove a flower or tree from the map, or to cut a multivac wire
  - [T] will enable the observatory scanner, showing any interesting signals present in the world
  - [SPACE] (or the play button in the top right corner), controls the simulation speed
 ";
@@ -49,6 +50,21 @@ pub fn warning(console_line: &mut EventWriter<PrintConsoleLine>, time: &Res<game
     console_line.send(PrintConsoleLine::new(WARNING.to_string()));
 }
 
+/// announce eruptions in the console
+pub fn volcano_warning(
+    mut console_line: EventWriter<PrintConsoleLine>,
+    mut events: EventReader<VolcanoEvent>,
+) {
+    for event in events.iter() {
+        if let VolcanoEvent::Erupted(location) = event {
+            console_line.send(PrintConsoleLine::new(format!(
+                "Crab: Eruption! There's lava pouring out around {}, {}",
+                location.x, location.y
+            )));
+        }
+    }
+}
+
 pub struct GameOver(bool, Timer);
 
 pub struct GameOverEvent;
@@ -143,7 +159,11 @@ impl bevy::prelude::Plugin for Plugin {
             .insert_resource(TextTimer(Timer::from_seconds(7.0, true)))
             .add_event::<Trigger>()
             .add_event::<GameOverEvent>()
-            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(timer))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(timer)
+                    .with_system(volcano_warning),
+            )
             .add_console_command::<InfoCommand, _, _>(info_system)
             .add_console_command::<CheatCommand, _, _>(cheat_system);
     }
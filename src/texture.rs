@@ -69,6 +69,27 @@ pub fn check_textures(
         );
         texture_atlas_map.insert("bee_c".into(), texture_atlases.add(bee_atlas));
 
+        // merged colonies only have a single frame, repeat it for every facing so drone sprite
+        // indices stay valid
+        let mut bee_atlas =
+            TextureAtlas::new_empty(texture_handles["bee_all"].clone(), Vec2::new(102., 104.));
+        for _ in 0..4 {
+            bee_atlas.add_texture(bevy::sprite::Rect {
+                min: Vec2::ZERO,
+                max: Vec2::new(102., 104.),
+            });
+        }
+        texture_atlas_map.insert("bee_all".into(), texture_atlases.add(bee_atlas));
+
+        // hornets only face left or right
+        let hornet_atlas = TextureAtlas::from_grid(
+            texture_handles["hornet-sheet"].clone(),
+            Vec2::new(102., 104.),
+            2,
+            1,
+        );
+        texture_atlas_map.insert("hornet".into(), texture_atlases.add(hornet_atlas));
+
         let bee_atlas = TextureAtlas::from_grid(
             texture_handles["flower-sheet"].clone(),
             Vec2::new(102., 104.),
@@ -0,0 +1,291 @@
+/// Volcano eruptions, lava flows and the rock they leave behind
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+
+use crate::{
+    director::{self, DirectorEvent},
+    draw,
+    hivemind::{Deaths, Drone, Forage, Ledger},
+    multivac::WireCutEvent,
+    world::{self, Flag, WorldMap, DIRS},
+    AppState,
+};
+
+#[derive(Debug)]
+pub struct Config {
+    /// average seconds between eruptions of a single volcano
+    pub eruption_period: f32,
+    /// heat of lava fresh out of a volcano
+    pub eruption_heat: u32,
+    /// the real-time period between lava updates
+    pub flow_clock: f32,
+    /// heat lost every time lava spreads to a neighbor
+    pub spread_cost: u32,
+    /// heat lost by every lava tile each lava update
+    pub cooling_rate: u32,
+    /// lava updates rock lasts before eroding back to empty ground
+    pub rock_lifetime: u32,
+}
+
+impl Config {
+    pub fn default() -> Self {
+        Self {
+            eruption_period: 300.0,
+            eruption_heat: 12,
+            flow_clock: 0.5,
+            spread_cost: 2,
+            cooling_rate: 1,
+            rock_lifetime: 600,
+        }
+    }
+}
+
+/// Time until a volcano next erupts
+#[derive(Component)]
+pub struct EruptionClock(pub Timer);
+
+/// Time until lava next spreads and cools
+pub struct FlowClock(pub Timer);
+
+/// What volcanoes are up to, for anything that wants to react to them
+#[derive(Debug, Clone, Copy)]
+pub enum VolcanoEvent {
+    Erupted(IVec2),
+    /// lava burned down whatever kind of tile was here
+    Burned(IVec2, Flag),
+    DroneKilled(Vec2),
+}
+
+/// Cover a tile in lava, burning whatever is on it. Returns the burned kinds, or None if lava can't
+/// flow here
+fn ignite(
+    map: &mut WorldMap,
+    ledger: &mut Ledger,
+    forage: &Forage,
+    pos: IVec2,
+    heat: u32,
+) -> Option<Flag> {
+    let flags = map.get_ivec2(pos)?;
+    if flags.intersects(
+        Flag::VOLCANO | Flag::COLONY_ALL | Flag::MULTIVAC | Flag::OUTPOST | Flag::LAVA,
+    ) {
+        return None;
+    }
+    ledger.retile(forage.on_tile(flags), 0);
+    map[pos] = Flag::LAVA;
+    map[pos].set_resource_quantity(heat);
+    Some(flags & Flag::KIND_MASK)
+}
+
+/// Spawn the lava entity for a freshly ignited tile, and report anything that burned
+fn burn(
+    commands: &mut Commands,
+    wires: &Query<(Entity, &world::Position), With<world::Wire>>,
+    volcano_events: &mut EventWriter<VolcanoEvent>,
+    wire_cuts: &mut EventWriter<WireCutEvent>,
+    pos: IVec2,
+    burned: Flag,
+) {
+    commands
+        .spawn()
+        .insert(world::Lava)
+        .insert(world::Position(pos.as_vec2()));
+
+    if burned.intersects(Flag::WIRE) {
+        for (entity, wire_pos) in wires.iter() {
+            if wire_pos.0.as_ivec2() == pos {
+                commands.entity(entity).despawn();
+            }
+        }
+        wire_cuts.send(WireCutEvent(pos));
+    }
+    if burned.intersects(Flag::FLOWER | Flag::TREE | Flag::WIRE) {
+        volcano_events.send(VolcanoEvent::Burned(pos, burned));
+    }
+}
+
+/// give any new volcano its eruption clock, staggered so volcanoes don't all erupt together
+pub fn setup(
+    mut commands: Commands,
+    config: Res<Config>,
+    query: Query<Entity, (With<world::Volcano>, Without<EruptionClock>)>,
+) {
+    let mut rng = SmallRng::from_entropy();
+    for entity in query.iter() {
+        let period = config.eruption_period * rng.gen_range(0.5..1.5);
+        commands
+            .entity(entity)
+            .insert(EruptionClock(Timer::from_seconds(period, true)));
+    }
+}
+
+/// Erupt volcanoes whose clock ran out, or that the director told to. Director eruptions only
+/// happen where it managed to raise a volcano
+#[allow(clippy::too_many_arguments)]
+pub fn erupt(
+    mut commands: Commands,
+    mut map: ResMut<WorldMap>,
+    time_step: Res<world::TimeStep>,
+    config: Res<Config>,
+    forage: Res<Forage>,
+    mut ledger: ResMut<Ledger>,
+    mut director_events: EventReader<DirectorEvent>,
+    mut volcano_events: EventWriter<VolcanoEvent>,
+    mut wire_cuts: EventWriter<WireCutEvent>,
+    wires: Query<(Entity, &world::Position), With<world::Wire>>,
+    mut volcanoes: Query<(&mut EruptionClock, &world::Position)>,
+) {
+    let time_step = time_step.into_inner();
+    let mut eruptions: Vec<IVec2> = director_events
+        .iter()
+        .filter_map(|event| match *event {
+            DirectorEvent::Eruption(location) => Some(location),
+            _ => None,
+        })
+        .filter(|location| {
+            map.get_ivec2(*location)
+                .map_or(false, |flags| flags.intersects(Flag::VOLCANO))
+        })
+        .collect();
+    for (mut clock, pos) in volcanoes.iter_mut() {
+        if clock.0.tick(time_step.into()).just_finished() {
+            eruptions.push(pos.0.as_ivec2());
+        }
+    }
+
+    for location in eruptions {
+        for dir in DIRS {
+            let neighbor = location + dir;
+            if let Some(burned) = ignite(
+                &mut map,
+                &mut ledger,
+                &forage,
+                neighbor,
+                config.eruption_heat,
+            ) {
+                burn(
+                    &mut commands,
+                    &wires,
+                    &mut volcano_events,
+                    &mut wire_cuts,
+                    neighbor,
+                    burned,
+                );
+            }
+        }
+        draw::ping_long(&mut commands, location);
+        volcano_events.send(VolcanoEvent::Erupted(location));
+        info!("volcano erupted at {}", location);
+    }
+}
+
+/// Spread lava to neighboring tiles, cool it into rock, and erode rock back into empty ground
+#[allow(clippy::too_many_arguments)]
+pub fn flow(
+    mut commands: Commands,
+    mut map: ResMut<WorldMap>,
+    time_step: Res<world::TimeStep>,
+    config: Res<Config>,
+    mut clock: ResMut<FlowClock>,
+    forage: Res<Forage>,
+    mut ledger: ResMut<Ledger>,
+    mut volcano_events: EventWriter<VolcanoEvent>,
+    mut wire_cuts: EventWriter<WireCutEvent>,
+    wires: Query<(Entity, &world::Position), With<world::Wire>>,
+) {
+    if !clock.0.tick(time_step.into_inner().into()).just_finished() {
+        return;
+    }
+
+    // snapshot first, so fresh lava doesn't keep flowing within the same update
+    let mut lava = Vec::new();
+    let mut rock = Vec::new();
+    for y in 0..map.h() {
+        for x in 0..map.w() {
+            let pos = IVec2::new(x as i32, y as i32);
+            if map[pos].intersects(Flag::LAVA) {
+                lava.push((pos, map[pos].get_resource_quantity()));
+            } else if map[pos].intersects(Flag::ROCK) {
+                rock.push((pos, map[pos].get_resource_quantity()));
+            }
+        }
+    }
+
+    let mut rng = SmallRng::from_entropy();
+    for (pos, heat) in lava {
+        if heat > config.spread_cost {
+            let neighbor = pos + DIRS[rng.gen_range(0..DIRS.len())];
+            if let Some(burned) = ignite(
+                &mut map,
+                &mut ledger,
+                &forage,
+                neighbor,
+                heat - config.spread_cost,
+            ) {
+                burn(
+                    &mut commands,
+                    &wires,
+                    &mut volcano_events,
+                    &mut wire_cuts,
+                    neighbor,
+                    burned,
+                );
+            }
+        }
+
+        if heat > config.cooling_rate {
+            map[pos].set_resource_quantity(heat - config.cooling_rate);
+        } else {
+            map[pos] = Flag::ROCK;
+            map[pos].set_resource_quantity(config.rock_lifetime);
+            commands
+                .spawn()
+                .insert(world::Rock)
+                .insert(world::Position(pos.as_vec2()));
+        }
+    }
+
+    for (pos, lifetime) in rock {
+        if lifetime > 0 {
+            map[pos].set_resource_quantity(lifetime - 1);
+        } else {
+            map[pos] = Flag::EMPTY;
+        }
+    }
+}
+
+/// drones that wander into lava don't come back out
+pub fn burn_drones(
+    map: Res<WorldMap>,
+    mut deaths: ResMut<Deaths>,
+    mut volcano_events: EventWriter<VolcanoEvent>,
+    drones: Query<(Entity, &world::Position), With<Drone>>,
+) {
+    for (entity, pos) in drones.iter() {
+        if let Some(flags) = map.get_vec2(pos.0) {
+            if flags.intersects(Flag::LAVA) && deaths.kill(entity) {
+                volcano_events.send(VolcanoEvent::DroneKilled(pos.0));
+            }
+        }
+    }
+}
+
+pub struct Plugin;
+
+impl bevy::prelude::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        let config = Config::default();
+        app.insert_resource(FlowClock(Timer::from_seconds(config.flow_clock, true)))
+            .insert_resource(config)
+            .add_event::<VolcanoEvent>()
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .before(world::Order::WorldUpdate)
+                    .with_system(setup)
+                    .with_system(erupt.after(director::DirectorOrder::Apply))
+                    .with_system(flow)
+                    .with_system(burn_drones),
+            );
+    }
+}
@@ -1,4 +1,9 @@
-use crate::{game, prelude::*};
+use crate::{
+    game,
+    hivemind::{Forage, Ledger},
+    multivac,
+    prelude::*,
+};
 /// global Information about the game world accessed by most modules
 use bevy::prelude::*;
 use bitflags::bitflags;
@@ -6,10 +11,16 @@ use bitflags::bitflags;
 use std::fs::File;
 use std::io::prelude::*;
 
-pub const APOCALYPSE_COUNTDOWN: f32 = 200.0;
-
 pub type WorldMap = Map<{ crate::WORLD_SIZE }, { crate::WORLD_SIZE }>;
 
+/// Offsets to the four neighbors of a tile: north, south, east, west
+pub const DIRS: [IVec2; 4] = [
+    bevy::math::const_ivec2!([0, 1]),
+    bevy::math::const_ivec2!([0, -1]),
+    bevy::math::const_ivec2!([1, 0]),
+    bevy::math::const_ivec2!([-1, 0]),
+];
+
 pub use nanoserde::{DeRon, SerRon};
 
 /// World time step value
@@ -121,6 +132,7 @@ pub enum Order {
 ///
 // NOTE: If we end up with more than 16 states, keep bumping integer size in Ucell, don't forget to
 // prepend the correct number of 0's to keep it all aligned and readable
+// NOTE: newer kinds are allocated from the top bit down so saved maps keep their quantity bits
 pub type FlagType = u32;
 bitflags! {
     #[derive(Component)]
@@ -136,13 +148,15 @@ bitflags! {
         const WIRE              = 0b00000000000000000000000010000000;
         const OUTPOST           = 0b00000000000000000000000100000000;
         const CONNECTED         = 0b00000000000000000000001000000000;
+        const LAVA              = 0b10000000000000000000000000000000;
+        const ROCK              = 0b01000000000000000000000000000000;
+        const STUMP             = 0b00100000000000000000000000000000;
 
         // helpers for valid sets of flags
-        const HIVE_FOOD         = 0b00000000000000000000000000000001;
         const MULTIVAC_FOOD     = 0b00000000000000000000000000000111;
         const WALL              = 0b00000000000000000000000000000110; // Volcanos and trees block pathing
         const COLONY_ALL        = 0b00000000000000000000000000111000; // Colonies can combine
-        const RESOURCE_QUANTITY = 0b11111111111111111111110000000000; // upper bits store generic resource quantity.
+        const RESOURCE_QUANTITY = 0b00011111111111111111110000000000; // upper bits store generic resource quantity.
         const KIND_MASK         = !Self::RESOURCE_QUANTITY.bits;
                                                       // Resource interpretation depends on flags
     }
@@ -150,7 +164,7 @@ bitflags! {
 
 impl Flag {
     pub const QUANTITY_SHIFT: u32 = Self::RESOURCE_QUANTITY.bits.trailing_zeros();
-    pub const MAX_RESOURCE_COUNT: u32 = 2u32.pow(Self::RESOURCE_QUANTITY.bits.leading_ones()) - 1;
+    pub const MAX_RESOURCE_COUNT: u32 = 2u32.pow(Self::RESOURCE_QUANTITY.bits.count_ones()) - 1;
 
     pub fn get_resource_quantity(&self) -> u32 {
         let bits = self.bits & Self::RESOURCE_QUANTITY.bits;
@@ -161,7 +175,7 @@ impl Flag {
         // clear quantity bits
         self.bits &= Self::KIND_MASK.bits;
 
-        self.bits |= count << Self::QUANTITY_SHIFT;
+        self.bits |= (count << Self::QUANTITY_SHIFT) & Self::RESOURCE_QUANTITY.bits;
     }
 }
 
@@ -208,6 +222,36 @@ pub fn despawn_tree(
     }
 }
 
+/// What's left of a harvested tree, the tile quantity is the sap left in it
+#[derive(Component)]
+pub struct Stump {
+    /// forest updates until it grows back into a tree
+    pub regrow: u32,
+}
+
+impl Stump {
+    /// Count down one forest update, true once it's time to grow back
+    pub fn grow(&mut self) -> bool {
+        if self.regrow == 0 {
+            return true;
+        }
+        self.regrow -= 1;
+        false
+    }
+}
+
+pub fn despawn_stump(
+    mut commands: Commands,
+    map: Res<WorldMap>,
+    query: Query<(Entity, &Position), With<Stump>>,
+) {
+    for (entity, pos) in query.iter() {
+        if !map[pos.0].intersects(Flag::STUMP) {
+            commands.entity(entity).despawn()
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct Volcano;
 
@@ -223,6 +267,38 @@ pub fn despawn_volcano(
     }
 }
 
+/// Molten rock flowing out of a volcano, the tile quantity is its remaining heat
+#[derive(Component)]
+pub struct Lava;
+
+pub fn despawn_lava(
+    mut commands: Commands,
+    map: Res<WorldMap>,
+    query: Query<(Entity, &Position), With<Lava>>,
+) {
+    for (entity, pos) in query.iter() {
+        if !map[pos.0].intersects(Flag::LAVA) {
+            commands.entity(entity).despawn()
+        }
+    }
+}
+
+/// Cooled lava, the tile quantity is how long until it erodes away
+#[derive(Component)]
+pub struct Rock;
+
+pub fn despawn_rock(
+    mut commands: Commands,
+    map: Res<WorldMap>,
+    query: Query<(Entity, &Position), With<Rock>>,
+) {
+    for (entity, pos) in query.iter() {
+        if !map[pos.0].intersects(Flag::ROCK) {
+            commands.entity(entity).despawn()
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct Colony;
 
@@ -382,7 +458,120 @@ impl<const W: usize, const H: usize> Map<W, H> {
         }
     }
 
-    pub fn initialize_map(mut commands: Commands, mut map: ResMut<WorldMap>) {
+    /// whether a tile blocks movement, everything off the map does
+    pub fn solid(&self, tile: IVec2) -> bool {
+        tile.x < 0
+            || tile.y < 0
+            || tile.x as usize >= W
+            || tile.y as usize >= H
+            || self[tile].intersects(Flag::WALL)
+    }
+
+    /// Move from `from` by `motion`, walking the tiles crossed on the way (DDA). Hitting a wall
+    /// stops movement across its face and slides the rest of the way along it
+    pub fn sweep(&self, from: Vec2, motion: Vec2) -> Vec2 {
+        // how far short of a wall face movement stops, so the end position rounds into open tile
+        const SKIN: f32 = 1e-3;
+        let mut pos = from;
+        let mut motion = motion;
+        // every hit blocks an axis, so a third pass can't hit anything
+        for _ in 0..2 {
+            if motion == Vec2::ZERO {
+                break;
+            }
+            let mut tile = pos.floor().as_ivec2();
+            let step = IVec2::new(motion.x.signum() as i32, motion.y.signum() as i32);
+            // fraction of the motion needed to cross a whole tile, and to reach the next face
+            let t_delta = Vec2::new(1.0 / motion.x.abs(), 1.0 / motion.y.abs());
+            let next_face = |p: f32, t: i32, m: f32| {
+                if m > 0.0 {
+                    (t as f32 + 1.0 - p) / m
+                } else if m < 0.0 {
+                    (t as f32 - p) / m
+                } else {
+                    f32::INFINITY
+                }
+            };
+            let mut t_max = Vec2::new(
+                next_face(pos.x, tile.x, motion.x),
+                next_face(pos.y, tile.y, motion.y),
+            );
+
+            let hit = loop {
+                let (t, x_axis) = if t_max.x < t_max.y {
+                    (t_max.x, true)
+                } else {
+                    (t_max.y, false)
+                };
+                if t > 1.0 {
+                    break None;
+                }
+                if x_axis {
+                    tile.x += step.x;
+                    t_max.x += t_delta.x;
+                } else {
+                    tile.y += step.y;
+                    t_max.y += t_delta.y;
+                }
+                if self.solid(tile) {
+                    break Some((t, x_axis));
+                }
+            };
+
+            let (t, x_axis) = match hit {
+                Some(hit) => hit,
+                // rounding can land a move that stops right at a wall face inside the wall
+                None if self.solid((pos + motion).floor().as_ivec2()) => return pos,
+                None => return pos + motion,
+            };
+            pos += motion * t;
+            let left = motion * (1.0 - t);
+            if x_axis {
+                pos.x -= step.x as f32 * SKIN;
+                motion = Vec2::new(0.0, left.y);
+            } else {
+                pos.y -= step.y as f32 * SKIN;
+                motion = Vec2::new(left.x, 0.0);
+            }
+        }
+        pos
+    }
+
+    /// Closest point to `pos` outside any wall, for things a wall sprang up under
+    pub fn escape(&self, pos: Vec2) -> Vec2 {
+        const SKIN: f32 = 1e-3;
+        let center = pos.floor().as_ivec2();
+        if !self.solid(center) {
+            return pos;
+        }
+        for r in 1..W.max(H) as i32 {
+            let mut best: Option<Vec2> = None;
+            for y in -r..=r {
+                for x in -r..=r {
+                    let tile = center + IVec2::new(x, y);
+                    if (x.abs() != r && y.abs() != r) || self.solid(tile) {
+                        continue;
+                    }
+                    let min = tile.as_vec2() + Vec2::splat(SKIN);
+                    let candidate = pos.clamp(min, min + Vec2::splat(1.0 - 2.0 * SKIN));
+                    if best.map_or(true, |b| candidate.distance(pos) < b.distance(pos)) {
+                        best = Some(candidate);
+                    }
+                }
+            }
+            if let Some(best) = best {
+                return best;
+            }
+        }
+        pos
+    }
+
+    pub fn initialize_map(
+        mut commands: Commands,
+        mut map: ResMut<WorldMap>,
+        forage: Res<Forage>,
+        mut ledger: ResMut<Ledger>,
+    ) {
         let save_map = SaveMap::load();
         *map = save_map.into_map();
         for y in 0..save_map.height {
@@ -401,6 +590,12 @@ impl<const W: usize, const H: usize> Map<W, H> {
                         .insert(Flower)
                         .insert(Position(Vec2::new(x as f32, y as f32)));
                 }
+                if map[y][x].intersects(Flag::VOLCANO) {
+                    commands
+                        .spawn()
+                        .insert(Volcano)
+                        .insert(Position(Vec2::new(x as f32, y as f32)));
+                }
             }
         }
         let xs = [0, W - 1];
@@ -442,6 +637,13 @@ impl<const W: usize, const H: usize> Map<W, H> {
                 }
             }
         }
+
+        // the food the world starts out with
+        for y in 0..map.h() {
+            for x in 0..map.w() {
+                ledger.retile(0, forage.on_tile(map[y][x]));
+            }
+        }
     }
 }
 
@@ -507,6 +709,7 @@ impl<const W: usize, const H: usize> std::ops::IndexMut<IVec2> for Map<W, H> {
 
 pub struct Cheat(pub bool);
 
+#[allow(clippy::too_many_arguments)]
 pub fn handle_event(
     mut commands: Commands,
     mut world_clicks: EventReader<WorldClickEvent>,
@@ -515,12 +718,17 @@ pub fn handle_event(
     mut cheat: ResMut<Cheat>,
     game_state: Res<crate::game::GameState>,
     mut game_writer: EventWriter<GameEvent>,
-    _query: Query<(Entity, &Position)>,
+    mut wire_cuts: EventWriter<multivac::WireCutEvent>,
+    forage: Res<Forage>,
+    mut ledger: ResMut<Ledger>,
+    wires: Query<(Entity, &Position), With<Wire>>,
+    trees: Query<(Entity, &Position), With<Tree>>,
 ) {
     let map = map.into_inner();
     for click in world_clicks.iter() {
         debug!("world click at {}, {} !", click.pos.x, click.pos.y);
         let click_pos = click.pos.floor();
+        let before = forage.on_tile(map[click_pos]);
         match click.btn {
             MouseButton::Left => {
                 if game_state.flower_ammo > 0 && map[click_pos].is_empty() {
@@ -530,6 +738,7 @@ pub fn handle_event(
                     commands.spawn().insert(Flower).insert(Position(click_pos));
                     map[click_pos].set_resource_quantity(Flower::MAX);
                     map[click_pos] |= Flag::FLOWER;
+                    ledger.retile(before, forage.on_tile(map[click_pos]));
                 } else {
                 }
             }
@@ -541,6 +750,7 @@ pub fn handle_event(
                     commands.spawn().insert(Tree).insert(Position(click_pos));
                     map[click_pos].set_resource_quantity(Tree::MAX);
                     map[click_pos] |= Flag::TREE;
+                    ledger.retile(before, forage.on_tile(map[click_pos]));
                 } else {
                 }
             }
@@ -553,6 +763,25 @@ pub fn handle_event(
                     }
                     map[click_pos].set_resource_quantity(0);
                     map[click_pos] = Flag::EMPTY;
+                    ledger.retile(before, 0);
+                    // a deleted tree is gone for good, it doesn't leave a stump
+                    for (entity, pos) in trees.iter() {
+                        if pos.0 == click_pos {
+                            commands.entity(entity).despawn();
+                        }
+                    }
+                } else if game_state.delete_ammo > 0 && map[click_pos].intersects(Flag::WIRE) {
+                    // sabotage, cut the multivac wire under the cursor
+                    if !cheat.0 {
+                        game_writer.send(GameEvent::Delete(ByteOp::Decrement));
+                    }
+                    map[click_pos] &= !(Flag::WIRE | Flag::CONNECTED);
+                    for (entity, pos) in wires.iter() {
+                        if pos.0 == click_pos {
+                            commands.entity(entity).despawn();
+                        }
+                    }
+                    wire_cuts.send(multivac::WireCutEvent(click_pos.as_ivec2()));
                 } else {
                 }
             }
@@ -575,43 +804,24 @@ pub fn handle_event(
     }
 }
 
-pub struct Apocalypse(pub Timer);
-
-/// Bring about the apocalypse
-pub fn start_apocalypse(
-    mut commands: Commands,
-    mut apoc: ResMut<Apocalypse>,
-    time_step: Res<TimeStep>,
-    mut map: ResMut<WorldMap>,
-) {
-    let position = IVec2::splat((crate::WORLD_SIZE / 2) as i32);
-    map[position] |= Flag::MULTIVAC;
-    //draw::ping(&mut commands, position);
-    if apoc.0.tick(time_step.into_inner().into()).just_finished() {
-        commands
-            .spawn()
-            .insert(Multivac)
-            .insert(Position(position.as_vec2()));
-    }
-}
-
 pub struct Plugin;
 
 impl bevy::prelude::Plugin for Plugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(WorldMap::new())
             .insert_resource(TimeStep::STOP)
-            .insert_resource(Apocalypse(Timer::from_seconds(APOCALYPSE_COUNTDOWN, false)))
             .insert_resource(Cheat(false))
             .add_system_set(
                 SystemSet::new()
                     .before(Order::WorldUpdate)
                     .with_system(update_timestep)
                     .with_system(handle_event)
-                    .with_system(start_apocalypse)
                     .with_system(animate_despawn_flower)
                     .with_system(despawn_tree)
-                    .with_system(despawn_volcano),
+                    .with_system(despawn_stump)
+                    .with_system(despawn_volcano)
+                    .with_system(despawn_lava)
+                    .with_system(despawn_rock),
             )
             .add_startup_system(WorldMap::initialize_map);
     }
@@ -635,4 +845,50 @@ mod tests {
         flowers.set_resource_quantity(Flag::MAX_RESOURCE_COUNT);
         assert_eq!(Flag::MAX_RESOURCE_COUNT, flowers.get_resource_quantity());
     }
+
+    #[test]
+    pub fn quantity_keeps_kind() {
+        let mut lava = Flag::LAVA | Flag::ROCK;
+        lava.set_resource_quantity(u32::MAX);
+        assert_eq!(Flag::MAX_RESOURCE_COUNT, lava.get_resource_quantity());
+        assert!(lava.contains(Flag::LAVA | Flag::ROCK));
+
+        lava.set_resource_quantity(0);
+        assert_eq!(Flag::LAVA | Flag::ROCK, lava);
+    }
+
+    #[test]
+    pub fn sweep_slides_along_walls() {
+        let mut map: Map<8, 8> = Map::new();
+        for y in 0..8 {
+            map[y][4] = Flag::TREE;
+        }
+        let end = map.sweep(Vec2::new(3.5, 2.5), Vec2::new(1.0, 1.0));
+        assert!(end.x < 4.0 && end.x > 3.9);
+        assert!((end.y - 3.5).abs() < 1e-4);
+    }
+
+    #[test]
+    pub fn drones_never_end_inside_walls() {
+        use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+        let mut rng = SmallRng::seed_from_u64(44);
+        let mut map: Map<16, 16> = Map::new();
+        for _ in 0..60 {
+            let (x, y) = (rng.gen_range(0..16), rng.gen_range(0..16));
+            map[y][x] = if rng.gen_bool(0.5) { Flag::TREE } else { Flag::VOLCANO };
+        }
+
+        let mut pos = Vec2::new(8.5, 8.5);
+        for _ in 0..100_000 {
+            // some ticks a wall springs up under the drone
+            if rng.gen_bool(0.001) {
+                map[pos] = Flag::TREE;
+            }
+            pos = map.escape(pos);
+            let motion = Vec2::new(rng.gen_range(-3.0..3.0), rng.gen_range(-3.0..3.0));
+            pos = map.sweep(pos, motion);
+            assert!(!map.solid(pos.floor().as_ivec2()), "inside a wall at {}", pos);
+        }
+    }
 }